@@ -0,0 +1,104 @@
+//! Compares the spatial grid broadphase against checking every pair of entities.
+//!
+//! Run with `cargo bench --bench collision`.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use gmtk_2024::collision::{
+    circle_collision, collision_system, rebuild_spatial_grid, Collider, CollisionEvent, SpatialGrid,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component, Clone)]
+struct Body;
+
+/// The old broadphase, kept here as a baseline.
+fn brute_force_system(
+    query_a: Query<(Entity, &GlobalTransform, &Collider, &Body)>,
+    query_b: Query<(Entity, &GlobalTransform, &Collider, &Body)>,
+    mut event_writer: EventWriter<CollisionEvent<Body, Body>>,
+) {
+    for (a_id, a_transform, a_collider, a_comp) in &query_a {
+        for (b_id, b_transform, b_collider, b_comp) in &query_b {
+            let bounds_overlap = circle_collision(
+                a_transform.translation().xy(),
+                a_collider.bounding_radius(),
+                b_transform.translation().xy(),
+                b_collider.bounding_radius(),
+            );
+
+            if a_id != b_id
+                && bounds_overlap
+                && a_collider.intersects(a_transform, b_collider, b_transform)
+            {
+                event_writer.send(CollisionEvent {
+                    a_id,
+                    a_comp: a_comp.clone(),
+                    b_id,
+                    b_comp: b_comp.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn create_world(count: usize) -> World {
+    let mut world = World::new();
+    world.init_resource::<SpatialGrid>();
+    world.init_resource::<Events<CollisionEvent<Body, Body>>>();
+
+    // Keep the density the same as the entity count grows
+    let size = (count as f32).sqrt() * 150.;
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..count {
+        let pos = Vec2::new(rng.gen_range(0.0..size), rng.gen_range(0.0..size));
+
+        world.spawn((
+            GlobalTransform::from_translation(pos.extend(0.)),
+            Collider::Circle { radius: 14. },
+            Body,
+        ));
+    }
+
+    world
+}
+
+fn time_schedule(world: &mut World, mut schedule: Schedule, iterations: u32) -> (Duration, usize) {
+    // Warm up, and initialise the system state
+    schedule.run(world);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        schedule.run(world);
+    }
+    let elapsed = start.elapsed() / iterations;
+
+    let mut events = world.resource_mut::<Events<CollisionEvent<Body, Body>>>();
+    let count = events.drain().count() / (iterations as usize + 1);
+
+    (elapsed, count)
+}
+
+fn main() {
+    for (count, iterations) in [(1_000, 50), (10_000, 5)] {
+        let mut world = create_world(count);
+
+        let mut brute_force = Schedule::default();
+        brute_force.add_systems(brute_force_system);
+        let (brute_force_time, brute_force_hits) =
+            time_schedule(&mut world, brute_force, iterations);
+
+        let mut grid = Schedule::default();
+        grid.add_systems((rebuild_spatial_grid, collision_system::<Body, Body>).chain());
+        let (grid_time, grid_hits) = time_schedule(&mut world, grid, iterations);
+
+        assert_eq!(brute_force_hits, grid_hits);
+
+        println!(
+            "{count:>6} entities: brute force {brute_force_time:>10.2?}, grid {grid_time:>10.2?} ({:.1}x faster, {grid_hits} collisions)",
+            brute_force_time.as_secs_f64() / grid_time.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Plays the game without a window, for testing and balancing.
+//!
+//! `cargo run --release --bin headless -- --seed 1 --ticks 20000`
+//! plays with a simple AI, or `--replay <path>` plays back a recording.
+
+use gmtk_2024::{
+    arg_value,
+    headless::{bot_plugin, headless_app, run_headless},
+    replay::{Playback, Replay},
+};
+
+fn main() {
+    let seed = arg_value("--seed").and_then(|seed| seed.parse().ok());
+    let max_ticks = arg_value("--ticks")
+        .and_then(|ticks| ticks.parse().ok())
+        .unwrap_or(64 * 60 * 10);
+
+    let mut app = headless_app(seed);
+
+    match arg_value("--replay") {
+        Some(path) => {
+            let replay = Replay::load(path.as_ref())
+                .unwrap_or_else(|err| panic!("Couldn't load replay {path}: {err}"));
+            app.insert_resource(Playback::new(replay));
+        }
+        None => {
+            app.add_plugins(bot_plugin);
+        }
+    }
+
+    let report = run_headless(&mut app, max_ticks);
+
+    println!(
+        "{} on wave {} after {:.2} secs ({} ticks)",
+        if report.died { "Died" } else { "Survived" },
+        report.wave,
+        report.survived.as_secs_f32(),
+        report.ticks,
+    );
+}
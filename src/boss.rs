@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    elements::Atom,
+    enemy::{enemy_movement_system, orbit_direction, Enemy},
+    health::Health,
+    molecule::Molecule,
+    shooting::{Shooter, Weapon},
+    state::{GameState, PlayingCleanup},
+    Player, Velocity,
+};
+
+/// An enemy that holds up its wave until it's destroyed.
+///
+/// Its first atom is the core, which can't be destroyed until every other atom has been,
+/// and it changes phase as its atoms are stripped away.
+#[derive(Component)]
+pub struct Boss {
+    pub phases: Vec<BossPhase>,
+    pub phase: usize,
+    pub atoms: usize,
+    pub max_health: f32,
+}
+
+impl Boss {
+    pub fn new(phases: Vec<BossPhase>, atoms: usize) -> Self {
+        Self {
+            phases,
+            phase: 0,
+            atoms,
+            max_health: 0.,
+        }
+    }
+
+    pub fn current(&self) -> Option<&BossPhase> {
+        self.phases.get(self.phase)
+    }
+
+    pub fn fire_rate(&self) -> f32 {
+        self.current().map_or(1., |phase| phase.fire_rate)
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct BossPhase {
+    /// The fraction of the boss's atoms left when this phase starts.
+    pub atoms_left: f32,
+    pub movement: BossMovement,
+    #[serde(default = "default_fire_rate")]
+    pub fire_rate: f32,
+    #[serde(default)]
+    pub weapon: Option<Weapon>,
+}
+
+fn default_fire_rate() -> f32 {
+    1.
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum BossMovement {
+    Seek,
+    /// Circle the player at this distance.
+    Orbit(f32),
+    /// Head for the player with this max speed.
+    Charge(f32),
+}
+
+#[derive(Component)]
+pub struct BossCore;
+
+pub fn boss_core_system(
+    mut cmds: Commands,
+    bosses: Query<&Children, (With<Boss>, Added<Children>)>,
+    mut atoms: Query<&mut Sprite, With<Atom>>,
+) {
+    for children in &bosses {
+        let Some(&core) = children.iter().find(|child| atoms.contains(**child)) else {
+            continue;
+        };
+
+        cmds.entity(core).insert(BossCore);
+
+        if let Ok(mut sprite) = atoms.get_mut(core) {
+            sprite.color = Color::linear_rgb(2., 0.6, 0.6);
+        }
+    }
+}
+
+pub fn boss_phase_system(
+    mut bosses: Query<(&mut Boss, &Molecule, &Children)>,
+    mut shooters: Query<&mut Shooter>,
+    healths: Query<&Health, With<Atom>>,
+) {
+    for (mut boss, molecule, children) in &mut bosses {
+        let max_health = healths
+            .iter_many(children)
+            .map(|health| health.max_health)
+            .sum();
+        boss.max_health = boss.max_health.max(max_health);
+
+        let left = molecule.elements.len() as f32 / boss.atoms as f32;
+
+        // Phases only go forwards, even if the boss regains atoms
+        while boss
+            .phases
+            .get(boss.phase + 1)
+            .is_some_and(|next| left <= next.atoms_left)
+        {
+            boss.phase += 1;
+        }
+
+        let Some(weapon) = boss.current().and_then(|phase| phase.weapon) else {
+            continue;
+        };
+
+        for child in children {
+            if let Ok(mut shooter) = shooters.get_mut(*child) {
+                shooter.weapon = weapon;
+            }
+        }
+    }
+}
+
+pub fn boss_movement_system(
+    mut bosses: Query<(&Boss, &Enemy, &mut Velocity, &Transform)>,
+    players: Query<&Transform, With<Player>>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let player = player.translation.xy();
+
+    for (boss, enemy, mut velocity, transform) in &mut bosses {
+        let offset = player - transform.translation.xy();
+
+        if offset.length() <= 0.0001 {
+            continue;
+        }
+
+        let dir = offset.normalize();
+
+        let accel = match boss.current().map(|phase| phase.movement) {
+            None | Some(BossMovement::Seek) => dir,
+            Some(BossMovement::Orbit(radius)) => orbit_direction(offset, radius),
+            Some(BossMovement::Charge(max_speed)) => {
+                velocity.max_speed = Some(max_speed);
+                dir
+            }
+        };
+
+        velocity.velocity += (accel * enemy.speed).extend(0.);
+    }
+}
+
+#[derive(Component)]
+pub struct BossBar;
+
+#[derive(Component)]
+pub struct BossBarFill;
+
+pub fn setup_boss_bar(mut cmds: Commands) {
+    cmds.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(18.),
+                left: Val::Percent(30.),
+                width: Val::Percent(40.),
+                height: Val::Px(16.),
+                border: UiRect::all(Val::Px(2.)),
+                ..Default::default()
+            },
+            border_color: BorderColor(Color::WHITE),
+            background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.5)),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        BossBar,
+        PlayingCleanup,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.9, 0.1, 0.1)),
+                ..Default::default()
+            },
+            BossBarFill,
+        ));
+    });
+}
+
+pub fn boss_bar_system(
+    bosses: Query<(&Boss, &Children)>,
+    healths: Query<&Health, With<Atom>>,
+    mut bars: Query<&mut Visibility, With<BossBar>>,
+    mut fills: Query<&mut Style, With<BossBarFill>>,
+) {
+    let (health, max_health) = bosses
+        .iter()
+        .map(|(boss, children)| {
+            let health: f32 = healths
+                .iter_many(children)
+                .map(|health| health.health.max(0.))
+                .sum();
+
+            (health, boss.max_health)
+        })
+        .fold((0., 0.), |(a, b), (c, d)| (a + c, b + d));
+
+    for mut visibility in &mut bars {
+        *visibility = if bosses.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+
+    if max_health > 0. {
+        for mut style in &mut fills {
+            style.width = Val::Percent(100. * (health / max_health).min(1.));
+        }
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        (
+            boss_core_system,
+            boss_phase_system,
+            boss_movement_system.after(enemy_movement_system),
+        ),
+    );
+}
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_boss_bar)
+        .add_systems(Update, boss_bar_system.run_if(in_state(GameState::Playing)));
+}
@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    elements::{ElementInfo, ElementTable, ATOM_RADIUS},
+    input::PlayerInput,
+    molecule::{cell_position, BuildMolecule, BuildMoleculeSet, Molecule},
+    state::{GameState, PlayingCleanup},
+    Player,
+};
+
+pub const BUILD_TIME_SCALE: f32 = 0.2;
+
+#[derive(Resource, Default)]
+pub struct BuildQueue(pub VecDeque<ElementInfo>);
+
+/// Place the next waiting atom in the slot nearest the cursor on each click,
+/// or every waiting atom wherever it fits once build mode is turned off.
+pub fn place_atom_system(
+    input: Res<PlayerInput>,
+    mut queue: ResMut<BuildQueue>,
+    players: Query<Entity, With<Player>>,
+    mut build_molecule_event: EventWriter<BuildMolecule>,
+    mut was_placing: Local<bool>,
+) {
+    let clicked = input.place && !*was_placing;
+    *was_placing = input.place;
+
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+
+    if !input.build_mode {
+        for element in queue.0.drain(..) {
+            build_molecule_event.send(BuildMolecule::Add {
+                target: player,
+                element,
+                near: None,
+            });
+        }
+
+        return;
+    }
+
+    let Some(target) = input.target.filter(|_| clicked) else {
+        return;
+    };
+
+    if let Some(element) = queue.0.pop_front() {
+        build_molecule_event.send(BuildMolecule::Add {
+            target: player,
+            element,
+            near: Some(target),
+        });
+    }
+}
+
+pub fn build_time_system(queue: Res<BuildQueue>, mut time: ResMut<Time<Virtual>>) {
+    let speed = if queue.0.is_empty() {
+        1.
+    } else {
+        BUILD_TIME_SCALE
+    };
+
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
+
+pub fn reset_build(mut queue: ResMut<BuildQueue>, mut time: ResMut<Time<Virtual>>) {
+    queue.0.clear();
+    time.set_relative_speed(1.);
+}
+
+#[derive(Component)]
+pub struct BuildGhost;
+
+#[derive(Component)]
+pub struct BuildText;
+
+pub fn setup_build_hud(mut cmds: Commands) {
+    cmds.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::linear_rgba(1., 1., 1., 0.5),
+                custom_size: Some(Vec2::splat(ATOM_RADIUS * 2.)),
+                ..Default::default()
+            },
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        BuildGhost,
+        PlayingCleanup,
+    ));
+
+    cmds.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Percent(5.),
+                justify_self: JustifySelf::Center,
+                ..Default::default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 32.,
+                    ..Default::default()
+                },
+            ),
+            ..Default::default()
+        },
+        BuildText,
+        PlayingCleanup,
+    ));
+}
+
+pub fn update_build_hud(
+    input: Res<PlayerInput>,
+    queue: Res<BuildQueue>,
+    table: Res<ElementTable>,
+    assets: Res<AssetServer>,
+    players: Query<(&Transform, &Molecule), With<Player>>,
+    mut ghosts: Query<
+        (&mut Transform, &mut Visibility, &mut Handle<Image>),
+        (With<BuildGhost>, Without<Player>),
+    >,
+    mut texts: Query<&mut Text, With<BuildText>>,
+) {
+    let next = queue.0.front().copied();
+
+    for mut text in &mut texts {
+        text.sections[0].value = match next {
+            Some(element) if queue.0.len() > 1 => {
+                format!("Click to place {element:?} ({} more)", queue.0.len() - 1)
+            }
+            Some(element) => format!("Click to place {element:?}"),
+            None if input.build_mode => "Build mode [B]".to_string(),
+            None => String::new(),
+        };
+    }
+
+    let Ok((mut ghost, mut visibility, mut texture)) = ghosts.get_single_mut() else {
+        return;
+    };
+
+    let slot = next.zip(input.target).zip(players.get_single().ok());
+
+    let Some(((element, target), (player, molecule))) = slot else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let near = player
+        .compute_affine()
+        .inverse()
+        .transform_point3(target.extend(0.))
+        .xy();
+    let slot = cell_position(molecule.free_cell(near));
+
+    ghost.translation = player.transform_point(slot.extend(1.));
+    *visibility = Visibility::Inherited;
+
+    let image = assets.load(&table[element].image_path);
+    if *texture != image {
+        *texture = image;
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<BuildQueue>()
+        .add_systems(OnExit(GameState::Playing), reset_build)
+        .add_systems(
+            FixedUpdate,
+            (place_atom_system, build_time_system)
+                .chain()
+                .in_set(BuildMoleculeSet::Placing)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_build_hud)
+        .add_systems(
+            Update,
+            update_build_hud.run_if(in_state(GameState::Playing)),
+        );
+}
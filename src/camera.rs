@@ -1,6 +1,6 @@
 use bevy::{core_pipeline::bloom::BloomSettings, prelude::*};
 
-use crate::{follow::follow_system, molecule::Molecule, velocity, Player};
+use crate::{follow::follow_system, molecule::Molecule, state::GameState, velocity, Player};
 
 #[derive(Component)]
 pub struct MainCamera;
@@ -95,5 +95,6 @@ pub fn plugin(app: &mut App) {
             follow_system::<MainCamera, Player, 10>.after(velocity::apply_velocity),
         )
         .add_systems(Update, zoom_camera)
+        .add_systems(OnEnter(GameState::Playing), setup_cursor)
         .add_systems(Update, update_cursor);
 }
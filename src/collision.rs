@@ -1,6 +1,8 @@
-use bevy::prelude::*;
-
-use crate::molecule::Molecule;
+use bevy::{
+    prelude::*,
+    transform::systems::{propagate_transforms, sync_simple_transforms},
+    utils::HashMap,
+};
 
 #[derive(Event)]
 pub struct CollisionEvent<A: Component + Clone, B: Component + Clone> {
@@ -14,61 +16,292 @@ pub fn circle_collision(a_center: Vec2, a_radius: f32, b_center: Vec2, b_radius:
     (a_center - b_center).length() - a_radius - b_radius <= 0.
 }
 
-pub fn collision_system<A: Component + Clone, B: Component + Clone>(
-    query_a: Query<(
+/// Entities without a collider never collide.
+#[derive(Component, Clone)]
+pub enum Collider {
+    Circle {
+        radius: f32,
+    },
+    /// A line along the local x axis from `-half_length` to `half_length`, with rounded ends.
+    Capsule {
+        half_length: f32,
+        radius: f32,
+    },
+    Compound(Vec<(Vec2, f32)>),
+}
+
+impl Collider {
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Collider::Circle { radius } => *radius,
+            Collider::Capsule {
+                half_length,
+                radius,
+            } => half_length + radius,
+            Collider::Compound(circles) => circles
+                .iter()
+                .map(|(offset, radius)| offset.length() + radius)
+                .fold(0., f32::max),
+        }
+    }
+
+    /// The shape as world space line segments with a radius,
+    /// where a circle is a segment with no length.
+    fn segments(&self, transform: &GlobalTransform) -> Vec<(Vec2, Vec2, f32)> {
+        let point = |local: Vec2| transform.transform_point(local.extend(0.)).xy();
+
+        match self {
+            Collider::Circle { radius } => {
+                vec![(point(Vec2::ZERO), point(Vec2::ZERO), *radius)]
+            }
+            Collider::Capsule {
+                half_length,
+                radius,
+            } => vec![(
+                point(Vec2::new(-half_length, 0.)),
+                point(Vec2::new(*half_length, 0.)),
+                *radius,
+            )],
+            Collider::Compound(circles) => circles
+                .iter()
+                .map(|&(offset, radius)| (point(offset), point(offset), radius))
+                .collect(),
+        }
+    }
+
+    pub fn intersects(
+        &self,
+        transform: &GlobalTransform,
+        other: &Collider,
+        other_transform: &GlobalTransform,
+    ) -> bool {
+        let other_segments = other.segments(other_transform);
+
+        self.segments(transform)
+            .into_iter()
+            .any(|(a_start, a_end, a_radius)| {
+                other_segments.iter().any(|&(b_start, b_end, b_radius)| {
+                    segment_distance(a_start, a_end, b_start, b_end) <= a_radius + b_radius
+                })
+            })
+    }
+}
+
+fn point_segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let line = end - start;
+
+    let t = if line.length_squared() > 0. {
+        ((point - start).dot(line) / line.length_squared()).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    (start + line * t).distance(point)
+}
+
+fn segment_distance(a_start: Vec2, a_end: Vec2, b_start: Vec2, b_end: Vec2) -> f32 {
+    let a = a_end - a_start;
+    let b = b_end - b_start;
+
+    // If each segment's ends are on opposite sides of the other, they cross
+    let crosses = a.perp_dot(b_start - a_start) * a.perp_dot(b_end - a_start) < 0.
+        && b.perp_dot(a_start - b_start) * b.perp_dot(a_end - b_start) < 0.;
+
+    if crosses {
+        return 0.;
+    }
+
+    point_segment_distance(a_start, b_start, b_end)
+        .min(point_segment_distance(a_end, b_start, b_end))
+        .min(point_segment_distance(b_start, a_start, a_end))
+        .min(point_segment_distance(b_end, a_start, a_end))
+}
+
+pub struct Layer;
+
+impl Layer {
+    pub const PLAYER: u32 = 1 << 0;
+    pub const ENEMY: u32 = 1 << 1;
+    pub const PLAYER_ATOM: u32 = 1 << 2;
+    pub const ENEMY_ATOM: u32 = 1 << 3;
+    pub const PLAYER_BULLET: u32 = 1 << 4;
+    pub const ENEMY_BULLET: u32 = 1 << 5;
+    pub const POWERUP: u32 = 1 << 6;
+    /// For things that aren't on either side.
+    pub const NEUTRAL: u32 = 1 << 7;
+}
+
+/// A collision only happens if both entities accept each other.
+/// Entities without this component collide with everything.
+#[derive(Component, Clone, Copy)]
+pub struct CollisionLayers {
+    pub memberships: u32,
+    pub filters: u32,
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self {
+            memberships: u32::MAX,
+            filters: u32::MAX,
+        }
+    }
+}
+
+impl CollisionLayers {
+    pub fn new(memberships: u32, filters: u32) -> Self {
+        Self {
+            memberships,
+            filters,
+        }
+    }
+
+    pub fn interacts(&self, other: &CollisionLayers) -> bool {
+        self.filters & other.memberships != 0 && other.filters & self.memberships != 0
+    }
+}
+
+pub struct GridEntry {
+    pub entity: Entity,
+    pub center: Vec2,
+    pub radius: f32,
+    pub layers: CollisionLayers,
+}
+
+/// A uniform grid of every collidable entity,
+/// so collision checks only need to look at nearby entities.
+///
+/// Entities are stored in the cell containing their center,
+/// and queries are widened by the largest radius in the grid to make up for it.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<GridEntry>>,
+    max_radius: f32,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(256.)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            max_radius: 0.,
+        }
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn clear(&mut self) {
+        // Keep the allocations around for the next tick
+        self.cells.values_mut().for_each(Vec::clear);
+        self.max_radius = 0.;
+    }
+
+    pub fn insert(&mut self, entity: Entity, center: Vec2, radius: f32, layers: CollisionLayers) {
+        self.max_radius = self.max_radius.max(radius);
+        self.cells
+            .entry(self.cell(center))
+            .or_default()
+            .push(GridEntry {
+                entity,
+                center,
+                radius,
+                layers,
+            });
+    }
+
+    pub fn query(&self, center: Vec2, radius: f32) -> impl Iterator<Item = &GridEntry> {
+        let reach = Vec2::splat(radius + self.max_radius);
+        let min = self.cell(center - reach);
+        let max = self.cell(center + reach);
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+}
+
+pub fn rebuild_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    query: Query<(
         Entity,
         &GlobalTransform,
-        Option<&Sprite>,
-        Option<&Molecule>,
-        &A,
+        &Collider,
+        Option<&CollisionLayers>,
     )>,
-    query_b: Query<(
+) {
+    grid.clear();
+
+    for (entity, transform, collider, layers) in &query {
+        grid.insert(
+            entity,
+            transform.translation().xy(),
+            collider.bounding_radius(),
+            layers.copied().unwrap_or_default(),
+        );
+    }
+}
+
+pub fn collision_system<A: Component + Clone, B: Component + Clone>(
+    grid: Res<SpatialGrid>,
+    query_a: Query<(
         Entity,
         &GlobalTransform,
-        Option<&Sprite>,
-        Option<&Molecule>,
-        &B,
+        &Collider,
+        Option<&CollisionLayers>,
+        &A,
     )>,
+    query_b: Query<(&GlobalTransform, &Collider, &B)>,
     mut event_writer: EventWriter<CollisionEvent<A, B>>,
 ) {
-    for (a_id, a_transform, a_sprite, a_molecule, a_comp) in &query_a {
+    for (a_id, a_transform, a_collider, a_layers, a_comp) in &query_a {
         let a_center = a_transform.translation().xy();
+        let a_radius = a_collider.bounding_radius();
+        let a_layers = a_layers.copied().unwrap_or_default();
 
-        let a_radius = match (a_molecule, a_sprite) {
-            (Some(molecule), _) => molecule.collision_radius(),
-            (
-                _,
-                Some(Sprite {
-                    custom_size: Some(size),
-                    ..
-                }),
-            ) => size.x,
-            _ => return,
-        };
-
-        for (b_id, b_transform, b_sprite, b_molecule, b_comp) in &query_b {
-            let b_center = b_transform.translation().xy();
-
-            let b_radius = match (b_molecule, b_sprite) {
-                (Some(molecule), _) => molecule.collision_radius(),
-                (
-                    _,
-                    Some(Sprite {
-                        custom_size: Some(size),
-                        ..
-                    }),
-                ) => size.x,
-                _ => return,
+        for b in grid.query(a_center, a_radius) {
+            if b.entity == a_id || !a_layers.interacts(&b.layers) {
+                continue;
+            }
+
+            let Ok((b_transform, b_collider, b_comp)) = query_b.get(b.entity) else {
+                continue;
             };
 
-            if circle_collision(a_center, a_radius, b_center, b_radius) {
+            // Only check the full shapes if the bounding circles overlap
+            if circle_collision(a_center, a_radius, b.center, b.radius)
+                && a_collider.intersects(a_transform, b_collider, b_transform)
+            {
                 event_writer.send(CollisionEvent {
                     a_id,
                     a_comp: a_comp.clone(),
-                    b_id,
+                    b_id: b.entity,
                     b_comp: b_comp.clone(),
                 });
             }
         }
     }
 }
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<SpatialGrid>().add_systems(
+        FixedPreUpdate,
+        (
+            // Global transforms are only propagated once a frame otherwise,
+            // so collisions would depend on the frame rate,
+            // and anything spawned since the last tick would be at the origin
+            (sync_simple_transforms, propagate_transforms),
+            rebuild_spatial_grid,
+        )
+            .chain(),
+    );
+}
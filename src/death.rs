@@ -170,13 +170,14 @@ pub fn death_cleanup_system(mut cmds: Commands, entities: Query<Entity, With<Dea
 }
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(
-        Update,
-        respawn_button_system.run_if(in_state(GameState::Death)),
-    )
-    .add_systems(
-        Update,
-        menu_button_system.run_if(in_state(GameState::Death)),
-    )
-    .add_systems(OnExit(GameState::Death), death_cleanup_system);
+    app.add_systems(OnEnter(GameState::Death), death_enter_system)
+        .add_systems(
+            Update,
+            respawn_button_system.run_if(in_state(GameState::Death)),
+        )
+        .add_systems(
+            Update,
+            menu_button_system.run_if(in_state(GameState::Death)),
+        )
+        .add_systems(OnExit(GameState::Death), death_cleanup_system);
 }
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    elements::{Atom, ElementInfo, ElementTable, ATOM_RADIUS},
+    health::Health,
+    molecule::molecule_health_system,
+    TimeToLive,
+};
+
+/// Released when a radioactive atom runs out of health,
+/// damaging atoms on any other molecule nearby.
+#[derive(Event)]
+pub struct RadiationBurst {
+    pub origin: Vec2,
+    pub radius: f32,
+    pub damage: f32,
+    /// The molecule the burst came from, which isn't damaged.
+    pub source: Entity,
+}
+
+/// An atom that ran out of health by decaying rather than being damaged,
+/// which is the only way it transmutes.
+#[derive(Component)]
+pub struct Decayed;
+
+pub fn decay_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    table: Res<ElementTable>,
+    mut atoms: Query<(Entity, &ElementInfo, &mut Health), With<Atom>>,
+) {
+    for (entity, &element, mut health) in &mut atoms {
+        if let Some(decay) = table[element].decay {
+            let alive = health.health > 0.;
+            health.health -= decay.rate * time.delta_seconds();
+
+            if alive && health.health <= 0. {
+                cmds.entity(entity).insert(Decayed);
+            }
+        }
+    }
+}
+
+pub fn radiation_burst_system(
+    mut events: EventReader<RadiationBurst>,
+    mut atoms: Query<(&GlobalTransform, &Parent, &mut Health), With<Atom>>,
+    mut cmds: Commands,
+    assets: Res<AssetServer>,
+) {
+    for burst in events.read() {
+        for (transform, parent, mut health) in &mut atoms {
+            if parent.get() == burst.source {
+                continue;
+            }
+
+            let distance = (transform.translation().xy() - burst.origin).length();
+
+            if distance <= burst.radius + ATOM_RADIUS {
+                health.damage(burst.damage);
+            }
+        }
+
+        cmds.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::linear_rgba(0.4, 2., 0.4, 0.3),
+                    custom_size: Some(Vec2::splat(burst.radius * 2.)),
+                    ..Default::default()
+                },
+                texture: assets.load("Circle.png"),
+                transform: Transform::from_translation(burst.origin.extend(0.5)),
+                ..Default::default()
+            },
+            TimeToLive::new(Duration::from_millis(250)),
+        ));
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_event::<RadiationBurst>().add_systems(
+        FixedUpdate,
+        (
+            decay_system.before(molecule_health_system),
+            radiation_burst_system.after(molecule_health_system),
+        ),
+    );
+}
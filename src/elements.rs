@@ -1,70 +1,152 @@
-use std::time::Duration;
+use std::{ops::Index, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
+    collision::{Collider, CollisionLayers, Layer},
     health::{Health, HealthSprite},
-    shooting::Shooter,
+    ron_asset::{reload_table_system, RonAssetLoader, RonTable, TableHandle},
+    shooting::{Shooter, Weapon},
 };
 
 #[derive(Component, Clone)]
 pub struct Atom;
 
-#[derive(Clone, Copy)]
+pub const ATOM_RADIUS: f32 = 32.;
+
+/// The stats for each element live in `assets/elements.ron`,
+/// but adding an element still needs a new variant here.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
 pub enum ElementInfo {
     Hydrogen,
     Iron,
     Uranium,
     Thorium,
     Radium,
+    Carbon,
 }
 
 impl ElementInfo {
-    pub fn image_path(&self) -> &'static str {
-        match self {
-            ElementInfo::Hydrogen => "ElementH.png",
-            ElementInfo::Iron => "ElementFe.png",
-            ElementInfo::Uranium => "ElementU.png",
-            ElementInfo::Thorium => "ElementTh.png",
-            ElementInfo::Radium => "ElementRa.png",
-        }
+    pub const ALL: [ElementInfo; 6] = [
+        ElementInfo::Hydrogen,
+        ElementInfo::Iron,
+        ElementInfo::Uranium,
+        ElementInfo::Thorium,
+        ElementInfo::Radium,
+        ElementInfo::Carbon,
+    ];
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ElementStats {
+    pub image_path: String,
+    pub max_health: f32,
+    pub firing_time: f32,
+    pub weapon: Weapon,
+    /// Extra max health given to each neighbouring atom in the same molecule.
+    #[serde(default)]
+    pub bond_health: f32,
+    #[serde(default)]
+    pub decay: Option<Decay>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct Decay {
+    pub rate: f32,
+    pub burst_radius: f32,
+    pub burst_damage: f32,
+    /// The element the atom becomes after bursting, if it survives at all.
+    #[serde(default)]
+    pub transmute: Option<ElementInfo>,
+}
+
+#[derive(Asset, TypePath, Resource, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct ElementTable(pub HashMap<ElementInfo, ElementStats>);
+
+impl Default for ElementTable {
+    fn default() -> Self {
+        ron::de::from_str(include_str!("../assets/elements.ron"))
+            .expect("Bundled elements.ron should be valid")
     }
+}
 
-    pub fn max_health(&self) -> f32 {
-        match self {
-            ElementInfo::Hydrogen => 100.,
-            ElementInfo::Iron => 300.,
-            ElementInfo::Uranium => 100.,
-            ElementInfo::Thorium => 200.,
-            ElementInfo::Radium => 300.,
-        }
+impl ElementTable {
+    pub fn missing(&self) -> Vec<ElementInfo> {
+        ElementInfo::ALL
+            .into_iter()
+            .filter(|element| !self.0.contains_key(element))
+            .collect()
     }
+}
+
+impl Index<ElementInfo> for ElementTable {
+    type Output = ElementStats;
+
+    fn index(&self, element: ElementInfo) -> &ElementStats {
+        self.0
+            .get(&element)
+            .unwrap_or_else(|| panic!("{element:?} is missing from elements.ron"))
+    }
+}
+
+impl RonTable for ElementTable {
+    /// Every element needs stats, or looking them up panics.
+    fn validate(&self) -> Result<(), String> {
+        let missing = self.missing();
 
-    pub fn firing_time(&self) -> f32 {
-        match self {
-            ElementInfo::Hydrogen => 1.,
-            ElementInfo::Iron => 1.,
-            ElementInfo::Uranium => 0.5,
-            ElementInfo::Thorium => 0.2,
-            ElementInfo::Radium => 0.1,
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("elements.ron is missing {missing:?}"))
         }
     }
+}
+
+pub fn load_element_table(mut cmds: Commands, assets: Res<AssetServer>) {
+    cmds.insert_resource(TableHandle::<ElementTable>(assets.load("elements.ron")));
+}
 
+/// Max health is left to `bond_health_system`, as it depends on the neighbouring atoms too.
+pub fn refresh_atoms_system(
+    table: Res<ElementTable>,
+    assets: Res<AssetServer>,
+    mut atoms: Query<(&ElementInfo, &mut Shooter, &mut Handle<Image>), With<Atom>>,
+) {
+    for (&element, mut shooter, mut texture) in &mut atoms {
+        let stats = &table[element];
+
+        shooter
+            .timer
+            .set_duration(Duration::from_secs_f32(stats.firing_time));
+        shooter.weapon = stats.weapon;
+
+        *texture = assets.load(&stats.image_path);
+    }
+}
+
+impl ElementInfo {
     pub fn build(
         &self,
         parent: &mut ChildBuilder,
         assets: &AssetServer,
+        table: &ElementTable,
+        rng: &mut impl Rng,
         offset: Vec2,
         is_player: bool,
     ) {
+        let stats = &table[*self];
+
         let sprite_bundle = SpriteBundle {
             sprite: Sprite {
                 color: Color::linear_rgb(1., 1., 1.),
-                custom_size: Some(Vec2::new(64., 64.)),
+                custom_size: Some(Vec2::splat(ATOM_RADIUS * 2.)),
                 ..Default::default()
             },
             transform: Transform::from_xyz(offset.x, offset.y, 0.),
-            texture: assets.load(self.image_path()),
+            texture: assets.load(&stats.image_path),
             ..Default::default()
         };
 
@@ -86,79 +168,61 @@ impl ElementInfo {
             HealthSprite,
         );
 
-        let shooter = Shooter::new(Duration::from_secs_f32(self.firing_time()));
-
-        match self {
-            ElementInfo::Hydrogen => parent
-                .spawn((
-                    sprite_bundle,
-                    Atom,
-                    Health::new(self.max_health()),
-                    Hydrogen,
-                    shooter,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(health);
-                }),
-            ElementInfo::Iron => parent
-                .spawn((
-                    sprite_bundle,
-                    Atom,
-                    Health::new(self.max_health()),
-                    Iron,
-                    shooter,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(health);
-                }),
-            ElementInfo::Uranium => parent
-                .spawn((
-                    sprite_bundle,
-                    Atom,
-                    Health::new(self.max_health()),
-                    Uranium,
-                    shooter,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(health);
-                }),
-            ElementInfo::Thorium => parent
-                .spawn((
-                    sprite_bundle,
-                    Atom,
-                    Health::new(self.max_health()),
-                    Thorium,
-                    shooter,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(health);
-                }),
-            ElementInfo::Radium => parent
-                .spawn((
-                    sprite_bundle,
-                    Atom,
-                    Health::new(self.max_health()),
-                    Radium,
-                    shooter,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(health);
-                }),
-        };
+        parent
+            .spawn((
+                sprite_bundle,
+                Atom,
+                Collider::Circle {
+                    radius: ATOM_RADIUS,
+                },
+                if is_player {
+                    CollisionLayers::new(
+                        Layer::PLAYER_ATOM,
+                        Layer::ENEMY_ATOM | Layer::ENEMY_BULLET,
+                    )
+                } else {
+                    CollisionLayers::new(
+                        Layer::ENEMY_ATOM,
+                        Layer::PLAYER_ATOM | Layer::PLAYER_BULLET,
+                    )
+                },
+                self.components(table, rng),
+            ))
+            .with_children(|parent| {
+                parent.spawn(health);
+            });
     }
-}
-
-#[derive(Component)]
-pub struct Hydrogen;
-
-#[derive(Component)]
-pub struct Iron;
-
-#[derive(Component)]
-pub struct Uranium;
 
-#[derive(Component)]
-pub struct Thorium;
+    pub fn components(
+        &self,
+        table: &ElementTable,
+        rng: &mut impl Rng,
+    ) -> (ElementInfo, Health, Shooter) {
+        let stats = &table[*self];
+
+        (
+            *self,
+            Health::new(stats.max_health),
+            Shooter::new(
+                Duration::from_secs_f32(stats.firing_time),
+                stats.weapon,
+                rng,
+            ),
+        )
+    }
+}
 
-#[derive(Component)]
-pub struct Radium;
+pub fn plugin(app: &mut App) {
+    app.init_asset::<ElementTable>()
+        .register_asset_loader(RonAssetLoader::<ElementTable>::new(&["elements.ron"]))
+        .init_resource::<ElementTable>()
+        .add_systems(Startup, load_element_table)
+        .add_systems(
+            Update,
+            (
+                reload_table_system::<ElementTable>,
+                refresh_atoms_system.run_if(resource_changed::<ElementTable>),
+            )
+                .chain(),
+        );
+}
@@ -1,11 +1,15 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{
-    collision::{collision_system, CollisionEvent},
+    boss::Boss,
+    collision::{collision_system, Collider, CollisionEvent},
+    decay::RadiationBurst,
     elements::Atom,
     health::Health,
-    molecule::Molecule,
+    powerup::{EnemyGrowth, Powerup, FORAGE_RANGE},
     shooting::{Bullet, CreateBullet, Shooter},
+    utils::intercept,
     Player, Velocity,
 };
 
@@ -20,24 +24,116 @@ impl Enemy {
     }
 }
 
+#[derive(Component, Clone, Copy, Default, Deserialize)]
+pub enum Behaviour {
+    #[default]
+    Seek,
+    /// Circle the player at this distance.
+    Orbit(f32),
+    /// Keep this far from the player, and shoot from further away than usual.
+    Snipe(f32),
+    /// Charge the player without shooting, and explode on contact.
+    Kamikaze { radius: f32, damage: f32 },
+    /// Come round to this far behind the player before closing in.
+    Flank(f32),
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Aim {
+    /// From 0 for aiming straight at the player, to 1 for a perfect intercept.
+    pub skill: f32,
+}
+
+pub const ENEMY_RANGE: f32 = 1200.;
+
+impl Behaviour {
+    /// How close the player has to be for the enemy to shoot,
+    /// on top of the player's size.
+    pub fn range(&self) -> Option<f32> {
+        match *self {
+            Behaviour::Snipe(distance) => Some(distance + ENEMY_RANGE / 2.),
+            Behaviour::Kamikaze { .. } => None,
+            _ => Some(ENEMY_RANGE),
+        }
+    }
+
+    /// The direction to accelerate in, and how hard compared to normal.
+    pub fn steer(&self, offset: Vec2, player_velocity: Vec2) -> (Vec2, f32) {
+        let dir = offset.normalize_or_zero();
+
+        match *self {
+            Behaviour::Seek => (dir, 1.),
+            Behaviour::Orbit(radius) => (orbit_direction(offset, radius), 1.),
+            Behaviour::Snipe(distance) => {
+                let towards = ((offset.length() - distance) / distance).clamp(-1., 1.);
+                (dir, towards)
+            }
+            Behaviour::Kamikaze { .. } => (dir, 2.),
+            Behaviour::Flank(distance) => {
+                // Fall back to seeking when the player isn't going anywhere
+                let Some(forward) = player_velocity.try_normalize() else {
+                    return (dir, 1.);
+                };
+
+                let rear = offset - forward * distance;
+
+                // Close in once behind the player
+                if rear.length() < distance || forward.dot(dir) > 0.5 {
+                    (dir, 1.)
+                } else {
+                    (rear.normalize(), 1.2)
+                }
+            }
+        }
+    }
+}
+
+/// The way to go round something at `radius`, `offset` away,
+/// while closing in on or backing off to the radius.
+pub fn orbit_direction(offset: Vec2, radius: f32) -> Vec2 {
+    let dir = offset.normalize_or_zero();
+    let towards = ((offset.length() - radius) / radius).clamp(-1., 1.);
+
+    (dir.perp() + dir * towards).normalize_or_zero()
+}
+
 pub fn enemy_movement_system(
-    mut enemies: Query<(&mut Velocity, &Transform, &Enemy)>,
-    players: Query<&Transform, With<Player>>,
+    mut enemies: Query<(&mut Velocity, &Transform, &Enemy, &Behaviour), Without<Boss>>,
+    players: Query<(&Transform, &Velocity), (With<Player>, Without<Enemy>)>,
+    powerups: Query<(&Transform, &Powerup), Without<Enemy>>,
+    growth: Res<EnemyGrowth>,
 ) {
-    let Ok(player) = players.get_single() else {
+    let Ok((player, player_velocity)) = players.get_single() else {
         return;
     };
+    let player_velocity = player_velocity.velocity.xy();
     let player = player.translation.xy();
 
-    for (mut velocity, origin, &Enemy { speed, .. }) in &mut enemies {
-        let offset = player - origin.translation.xy();
+    for (mut velocity, origin, &Enemy { speed, .. }, behaviour) in &mut enemies {
+        let pos = origin.translation.xy();
+        let offset = player - pos;
 
         if offset.length() <= 0.0001 {
             continue;
         }
 
-        let dir = offset.normalize();
-        let accel = speed * dir;
+        // Go for nearby powerups while enemies can still grow this wave
+        let powerup = (growth.remaining > 0)
+            .then(|| {
+                powerups
+                    .iter()
+                    .filter(|(_, powerup)| matches!(powerup, Powerup::AddAtom(_)))
+                    .map(|(powerup, _)| powerup.translation.xy() - pos)
+                    .filter(|offset| offset.length() < FORAGE_RANGE)
+                    .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            })
+            .flatten();
+
+        let (dir, strength) = match powerup {
+            Some(offset) => (offset.normalize_or_zero(), 1.),
+            None => behaviour.steer(offset, player_velocity),
+        };
+        let accel = speed * strength * dir;
 
         velocity.velocity += Vec3::new(accel.x, accel.y, 0.);
     }
@@ -45,34 +141,48 @@ pub fn enemy_movement_system(
 
 pub fn enemy_shooting_system(
     time: Res<Time>,
-    enemies: Query<(), With<Enemy>>,
+    enemies: Query<(Option<&Behaviour>, Option<&Aim>, Option<&Boss>), With<Enemy>>,
     mut shooters: Query<(&GlobalTransform, &Parent, &mut Shooter)>,
-    players: Query<(&Transform, &Molecule), With<Player>>,
+    players: Query<(&Transform, &Velocity, Option<&Collider>), With<Player>>,
     create_bullet: Res<CreateBullet>,
     mut cmds: Commands,
 ) {
-    let Ok((player, player_molecule)) = players.get_single() else {
+    let Ok((player, player_velocity, player_collider)) = players.get_single() else {
         return;
     };
     let target = player.translation.xy();
+    let player_velocity = player_velocity.velocity.xy();
 
-    let player_radius = player_molecule.collision_radius();
+    let player_radius = player_collider.map_or(0., Collider::bounding_radius);
+
+    for (shooter_trans, parent, mut shooter) in &mut shooters {
+        let Ok((behaviour, aim, boss)) = enemies.get(parent.get()) else {
+            continue;
+        };
 
-    for (shooter_trans, _, mut shooter) in shooters
-        .iter_mut()
-        .filter(|(_, parent, _)| enemies.contains(parent.get()))
-    {
-        if shooter.timer.tick(time.delta()).finished() {
+        let Some(range) = behaviour.copied().unwrap_or_default().range() else {
+            continue;
+        };
+
+        let fire_rate = boss.map_or(1., Boss::fire_rate);
+
+        if shooter.tick(time.delta().mul_f32(fire_rate)) {
             let origin = shooter_trans.translation().xy();
             let delta = target - origin;
 
-            if delta.length() >= 1200. + player_radius {
+            if delta.length() >= range + player_radius {
                 continue;
             }
 
-            let dir = delta.normalize();
+            let dir = match aim {
+                Some(aim) => intercept(delta, player_velocity, shooter.weapon.bullet_speed())
+                    .map_or(delta, |lead| delta.lerp(lead, aim.skill)),
+                None => delta,
+            };
 
-            cmds.run_system_with_input(create_bullet.0, (origin, dir, Bullet::FromEnemy));
+            shooter
+                .weapon
+                .fire(&mut cmds, &create_bullet, origin, dir, Bullet::FromEnemy);
         }
     }
 }
@@ -112,10 +222,10 @@ pub fn enemy_player_collision_system(
 
     for event in events.read() {
         let Ok(player_trans) = players.get(event.a_id) else {
-            return;
+            continue;
         };
         let Ok((mut enemy_vel, enemy_trans)) = enemies.get_mut(event.b_id) else {
-            return;
+            continue;
         };
 
         let difference = (enemy_trans.translation.xy() - player_trans.translation.xy()).normalize();
@@ -125,29 +235,50 @@ pub fn enemy_player_collision_system(
     }
 }
 
+pub fn kamikaze_system(
+    mut events: EventReader<CollisionEvent<Player, Enemy>>,
+    enemies: Query<(&Behaviour, &GlobalTransform), With<Enemy>>,
+    mut burst_event: EventWriter<RadiationBurst>,
+    mut cmds: Commands,
+) {
+    for event in events.read() {
+        let Ok((&Behaviour::Kamikaze { radius, damage }, transform)) = enemies.get(event.b_id)
+        else {
+            continue;
+        };
+
+        burst_event.send(RadiationBurst {
+            origin: transform.translation().xy(),
+            radius,
+            damage,
+            source: event.b_id,
+        });
+
+        if let Some(entity) = cmds.get_entity(event.b_id) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
 pub fn enemy_player_damage_system(
     mut events: EventReader<CollisionEvent<Atom, Atom>>,
     mut healths: Query<&mut Health>,
     parents: Query<&Parent>,
     players: Query<&Transform, (With<Player>, Without<Enemy>)>,
 ) {
+    // Layers mean only player atoms touching enemy atoms get here,
+    // so this just has to find which side is the player.
     for event in events.read() {
         let Ok(a_parent) = parents.get(event.a_id) else {
-            return;
-        };
-        let Ok(b_parent) = parents.get(event.b_id) else {
-            return;
+            continue;
         };
 
-        let a_is_player = players.contains(a_parent.get());
-        let b_is_player = players.contains(b_parent.get());
-
-        if a_is_player && !b_is_player {
+        if players.contains(a_parent.get()) {
             let Ok(mut a_health) = healths.get_mut(event.a_id) else {
-                return;
+                continue;
             };
 
-            a_health.health -= 0.5;
+            a_health.damage(0.5);
         }
     }
 }
@@ -165,9 +296,9 @@ pub fn plugin(app: &mut App) {
                 collision_system::<Enemy, Enemy>,
                 enemy_internal_collision_system,
                 collision_system::<Player, Enemy>,
-                enemy_player_collision_system,
+                (enemy_player_collision_system, kamikaze_system),
             )
                 .chain(),
         )
-        .add_systems(Update, enemy_shooting_system);
+        .add_systems(FixedUpdate, enemy_shooting_system);
 }
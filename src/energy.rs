@@ -1,12 +1,199 @@
 use bevy::prelude::*;
 
+use crate::{
+    elements::{Atom, ElementInfo, ElementTable},
+    health::Health,
+    input::PlayerInput,
+    molecule::Molecule,
+    powerup::{spawn_powerup, spawn_powerup_system, Powerup},
+    rng::{GameRng, RngStream},
+    state::{GameState, PlayingCleanup},
+    status::{repair, shield, StatusEffects},
+    utils::random_in_donut,
+    Player,
+};
+
 #[derive(Resource, Default)]
 pub struct Energy(pub f32);
 
+pub fn powerup_cost(atoms: usize) -> f32 {
+    -30. + 50. * (atoms as f32).sqrt()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Purchase {
+    Repair,
+    Shield,
+    /// A powerup of this element, close by.
+    Atom(ElementInfo),
+}
+
+impl Purchase {
+    /// Everything that can be bought, in hotkey order.
+    pub const ALL: [Purchase; 6] = [
+        Purchase::Repair,
+        Purchase::Shield,
+        Purchase::Atom(ElementInfo::Iron),
+        Purchase::Atom(ElementInfo::Thorium),
+        Purchase::Atom(ElementInfo::Radium),
+        Purchase::Atom(ElementInfo::Carbon),
+    ];
+
+    pub fn cost(&self, atoms: usize) -> f32 {
+        let scale = match self {
+            Purchase::Repair => 1.5,
+            Purchase::Shield => 2.,
+            Purchase::Atom(_) => 1.5,
+        };
+
+        scale * powerup_cost(atoms)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Purchase::Repair => "Repair".to_string(),
+            Purchase::Shield => "Shield".to_string(),
+            Purchase::Atom(element) => format!("{element:?}"),
+        }
+    }
+}
+
 pub fn reset_energy(mut energy: ResMut<Energy>) {
     energy.0 = 0.;
 }
 
+pub fn purchase_system(
+    mut cmds: Commands,
+    input: Res<PlayerInput>,
+    mut energy: ResMut<Energy>,
+    mut players: Query<(&Transform, &Molecule, &Children, &mut StatusEffects), With<Player>>,
+    mut healths: Query<&mut Health, With<Atom>>,
+    assets: Res<AssetServer>,
+    table: Res<ElementTable>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(purchase) = input.buy else {
+        return;
+    };
+
+    let Ok((transform, molecule, children, mut effects)) = players.get_single_mut() else {
+        return;
+    };
+
+    let cost = purchase.cost(molecule.elements.len());
+
+    if energy.0 < cost {
+        return;
+    }
+
+    energy.0 -= cost;
+
+    match purchase {
+        Purchase::Repair => repair(children, &mut healths),
+        Purchase::Shield => shield(children, &mut healths, &mut effects),
+        Purchase::Atom(element) => {
+            let rng = rng.stream(RngStream::Powerups);
+
+            spawn_powerup(
+                &mut cmds,
+                &assets,
+                &table,
+                Powerup::AddAtom(element),
+                transform.translation.xy() + random_in_donut(rng, 300., 500.),
+                cost,
+            );
+        }
+    }
+}
+
+pub fn shield_tint_system(
+    players: Query<&Children, With<Player>>,
+    mut atoms: Query<(&Health, &mut Sprite), With<Atom>>,
+) {
+    for children in &players {
+        let mut atoms = atoms.iter_many_mut(children);
+        while let Some((health, mut sprite)) = atoms.fetch_next() {
+            sprite.color = if health.shield > 0. {
+                Color::linear_rgb(0.6, 1.4, 2.)
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct EnergyText;
+
+pub fn setup_energy_text(mut cmds: Commands) {
+    let style = |font_size| TextStyle {
+        font_size,
+        ..Default::default()
+    };
+
+    let mut sections = vec![TextSection::new("", style(48.))];
+    sections.extend(Purchase::ALL.map(|_| TextSection::new("", style(24.))));
+
+    cmds.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Percent(5.),
+                left: Val::Percent(5.),
+                ..Default::default()
+            },
+            text: Text {
+                sections,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        EnergyText,
+        PlayingCleanup,
+    ));
+}
+
+pub fn update_energy_text(
+    mut texts: Query<&mut Text, With<EnergyText>>,
+    energy: Res<Energy>,
+    players: Query<&Molecule, With<Player>>,
+) {
+    let Ok(mut text) = texts.get_single_mut() else {
+        return;
+    };
+
+    let atoms = players
+        .get_single()
+        .map_or(1, |molecule| molecule.elements.len());
+
+    text.sections[0].value = format!("Energy {:.0}\n", energy.0.floor());
+
+    for (i, purchase) in Purchase::ALL.iter().enumerate() {
+        let cost = purchase.cost(atoms);
+        let section = &mut text.sections[i + 1];
+
+        section.value = format!("[{}] {} {:.0}\n", i + 1, purchase.name(), cost.ceil());
+        section.style.color = if energy.0 >= cost {
+            Color::WHITE
+        } else {
+            Color::srgb(0.4, 0.4, 0.4)
+        };
+    }
+}
+
 pub fn plugin(app: &mut App) {
-    app.init_resource::<Energy>();
+    app.init_resource::<Energy>().add_systems(
+        FixedUpdate,
+        purchase_system
+            .before(spawn_powerup_system)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_energy_text)
+        .add_systems(
+            Update,
+            (update_energy_text, shield_tint_system).run_if(in_state(GameState::Playing)),
+        );
 }
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use bevy::{app::PluginsState, prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+use crate::{
+    enemy::Enemy,
+    input::{InputSet, PlayerInput},
+    powerup::Powerup,
+    rng::FixedSeed,
+    state::GameState,
+    timer::GameTimer,
+    wave::WaveCount,
+    Player,
+};
+
+const BOT_PANIC_DISTANCE: f32 = 900.;
+
+/// Each update advances time by one fixed timestep at normal speed,
+/// and the run starts in [`GameState::Playing`].
+pub fn headless_app(seed: Option<u64>) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        StatesPlugin,
+    ))
+    // Sprites still load their textures, they just never get drawn
+    .init_asset::<Image>()
+    .add_plugins(crate::gameplay_plugins)
+    .insert_resource(FixedSeed(seed));
+
+    let timestep = app.world().resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        .world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    app
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunReport {
+    pub wave: usize,
+    pub survived: Duration,
+    pub ticks: usize,
+    pub died: bool,
+}
+
+pub fn run_headless(app: &mut App, max_ticks: usize) -> RunReport {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+
+    // The first update only enters the playing state
+    app.update();
+
+    // Slowed down time means an update doesn't always run a fixed tick
+    let fixed_ticks = |app: &App| {
+        let time = app.world().resource::<Time<Fixed>>();
+        (time.elapsed().as_nanos() / time.timestep().as_nanos()) as usize
+    };
+    let start = fixed_ticks(app);
+    let mut ticks = 0;
+
+    while ticks < max_ticks && *app.world().resource::<State<GameState>>() == GameState::Playing {
+        app.update();
+        ticks = fixed_ticks(app) - start;
+    }
+
+    let world = app.world();
+
+    RunReport {
+        wave: world.resource::<WaveCount>().0,
+        survived: world.resource::<GameTimer>().0.elapsed(),
+        ticks,
+        died: *world.resource::<State<GameState>>() == GameState::Death,
+    }
+}
+
+pub fn bot_input_system(
+    mut input: ResMut<PlayerInput>,
+    player: Query<&Transform, With<Player>>,
+    enemies: Query<&Transform, With<Enemy>>,
+    powerups: Query<&Transform, With<Powerup>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let pos = player.translation.xy();
+
+    let closest = |transforms: &mut dyn Iterator<Item = &Transform>| {
+        transforms
+            .map(|transform| transform.translation.xy())
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+    };
+
+    let enemy = closest(&mut enemies.iter());
+    let powerup = closest(&mut powerups.iter());
+
+    let dir = match (enemy, powerup) {
+        (Some(enemy), _) if enemy.distance(pos) < BOT_PANIC_DISTANCE => pos - enemy,
+        (_, Some(powerup)) => powerup - pos,
+        _ => Vec2::ZERO,
+    };
+
+    *input = PlayerInput {
+        up: dir.y > 0.,
+        down: dir.y < 0.,
+        left: dir.x < 0.,
+        right: dir.x > 0.,
+        target: enemy,
+        ..Default::default()
+    };
+}
+
+pub fn bot_plugin(app: &mut App) {
+    app.add_systems(FixedPreUpdate, bot_input_system.in_set(InputSet));
+}
@@ -7,6 +7,7 @@ pub struct HealthSprite;
 pub struct Health {
     pub health: f32,
     pub max_health: f32,
+    pub shield: f32,
 }
 
 impl Health {
@@ -14,8 +15,15 @@ impl Health {
         Self {
             health,
             max_health: health,
+            shield: 0.,
         }
     }
+
+    pub fn damage(&mut self, damage: f32) {
+        let absorbed = damage.min(self.shield);
+        self.shield -= absorbed;
+        self.health -= damage - absorbed;
+    }
 }
 
 pub fn health_texture_system(
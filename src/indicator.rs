@@ -0,0 +1,277 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    boss::Boss,
+    enemy::Enemy,
+    powerup::Powerup,
+    state::{GameState, PlayingCleanup},
+    MainCamera, Player,
+};
+
+const INDICATOR_SIZE: f32 = 128.;
+
+/// How far in from the edge of the screen the middle of an indicator sits.
+const INDICATOR_INSET: f32 = 128.;
+
+/// Indicators shrink and fade from this distance until `FAR`.
+const NEAR: f32 = 1000.;
+const FAR: f32 = 8000.;
+
+const CLUSTER_SIZE: f32 = 1500.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IndicatorKind {
+    Powerup,
+    Boss,
+    Enemies,
+}
+
+impl IndicatorKind {
+    const SLOTS: [(IndicatorKind, usize); 3] = [
+        (IndicatorKind::Powerup, 3),
+        (IndicatorKind::Boss, 2),
+        (IndicatorKind::Enemies, 1),
+    ];
+
+    fn color(&self) -> Color {
+        match self {
+            IndicatorKind::Powerup => Color::srgb(0.6, 1., 0.6),
+            IndicatorKind::Boss => Color::srgb(1., 0.3, 0.3),
+            IndicatorKind::Enemies => Color::srgb(1., 0.7, 0.3),
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            IndicatorKind::Powerup => "Circle.png",
+            IndicatorKind::Boss => "IconBoss.png",
+            IndicatorKind::Enemies => "IconEnemies.png",
+        }
+    }
+}
+
+/// There's a fixed number of indicators of each kind,
+/// each showing the `slot`th closest target of that kind.
+#[derive(Component)]
+pub struct Indicator {
+    pub kind: IndicatorKind,
+    pub slot: usize,
+    /// Smooths out the arrow's movement.
+    last_dir: Option<Vec2>,
+}
+
+#[derive(Component)]
+pub struct IndicatorArrow;
+
+#[derive(Component)]
+pub struct IndicatorIcon;
+
+/// Where a line from the middle of the screen in `dir` reaches `inset` from its edge,
+/// in UI coordinates.
+pub fn screen_edge(dir: Vec2, size: Vec2, inset: f32) -> Vec2 {
+    let half = (size / 2. - inset).max(Vec2::ZERO);
+    let dir = dir.normalize_or_zero();
+    let abs = dir.abs();
+
+    // Whichever edge it reaches first
+    let point = dir * (half.x / abs.x).min(half.y / abs.y);
+
+    size / 2. + Vec2::new(point.x, -point.y)
+}
+
+pub fn setup_indicators(mut cmds: Commands, assets: Res<AssetServer>) {
+    for (kind, slots) in IndicatorKind::SLOTS {
+        for slot in 0..slots {
+            cmds.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(INDICATOR_SIZE),
+                        height: Val::Px(INDICATOR_SIZE),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                Indicator {
+                    kind,
+                    slot,
+                    last_dir: None,
+                },
+                PlayingCleanup,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    ImageBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            ..Default::default()
+                        },
+                        image: UiImage {
+                            texture: assets.load("Arrow.png"),
+                            color: kind.color(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    IndicatorArrow,
+                ));
+
+                parent.spawn((
+                    ImageBundle {
+                        style: Style {
+                            width: Val::Percent(40.),
+                            height: Val::Percent(40.),
+                            ..Default::default()
+                        },
+                        image: UiImage::new(assets.load(kind.icon())),
+                        ..Default::default()
+                    },
+                    IndicatorIcon,
+                ));
+            });
+        }
+    }
+}
+
+fn enemy_clusters(enemies: impl Iterator<Item = Vec2>) -> Vec<Vec2> {
+    let mut cells: HashMap<IVec2, (Vec2, f32)> = HashMap::new();
+
+    for pos in enemies {
+        let cell = (pos / CLUSTER_SIZE).floor().as_ivec2();
+        let (sum, count) = cells.entry(cell).or_default();
+        *sum += pos;
+        *count += 1.;
+    }
+
+    cells
+        .into_values()
+        .map(|(sum, count)| sum / count)
+        .collect()
+}
+
+pub fn indicator_system(
+    mut indicators: Query<(&mut Indicator, &mut Style, &mut Visibility, &Children)>,
+    mut arrows: Query<(&mut Transform, &mut UiImage), With<IndicatorArrow>>,
+    mut icons: Query<&mut UiImage, (With<IndicatorIcon>, Without<IndicatorArrow>)>,
+    powerups: Query<(&Transform, &Handle<Image>), (With<Powerup>, Without<IndicatorArrow>)>,
+    bosses: Query<&Transform, (With<Boss>, Without<IndicatorArrow>)>,
+    enemies: Query<&Transform, (With<Enemy>, Without<Boss>, Without<IndicatorArrow>)>,
+    players: Query<(), With<Player>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    windows: Query<&Window>,
+    assets: Res<AssetServer>,
+) {
+    let (Ok((camera, camera_transform)), Ok(window)) = (cameras.get_single(), windows.get_single())
+    else {
+        return;
+    };
+
+    let size = window.size();
+    let center = camera_transform.translation().xy();
+
+    let off_screen = |pos: Vec2| {
+        !camera
+            .world_to_viewport(camera_transform, pos.extend(0.))
+            .is_some_and(|screen| Rect::from_corners(Vec2::ZERO, size).contains(screen))
+    };
+
+    // The targets of each kind, closest first, with their own icon if they have one
+    let mut targets: HashMap<IndicatorKind, Vec<(Vec2, Option<Handle<Image>>)>> = HashMap::new();
+
+    if !players.is_empty() {
+        targets.insert(
+            IndicatorKind::Powerup,
+            powerups
+                .iter()
+                .map(|(transform, image)| (transform.translation.xy(), Some(image.clone())))
+                .collect(),
+        );
+        targets.insert(
+            IndicatorKind::Boss,
+            bosses
+                .iter()
+                .map(|transform| (transform.translation.xy(), None))
+                .collect(),
+        );
+        targets.insert(
+            IndicatorKind::Enemies,
+            enemy_clusters(enemies.iter().map(|transform| transform.translation.xy()))
+                .into_iter()
+                .map(|pos| (pos, None))
+                .collect(),
+        );
+    }
+
+    for list in targets.values_mut() {
+        list.retain(|(pos, _)| off_screen(*pos));
+        list.sort_by(|(a, _), (b, _)| {
+            a.distance_squared(center)
+                .total_cmp(&b.distance_squared(center))
+        });
+    }
+
+    for (mut indicator, mut style, mut visibility, children) in &mut indicators {
+        let target = targets
+            .get(&indicator.kind)
+            .and_then(|list| list.get(indicator.slot));
+
+        let Some((pos, image)) = target else {
+            *visibility = Visibility::Hidden;
+            indicator.last_dir = None;
+            continue;
+        };
+
+        let offset = *pos - center;
+        let dir = offset.normalize_or_zero();
+        let dir = match indicator.last_dir {
+            Some(last) => last.lerp(dir, 0.1).normalize_or_zero(),
+            None => dir,
+        };
+        indicator.last_dir = Some(dir);
+
+        // Further away is smaller and fainter
+        let far = ((offset.length() - NEAR) / (FAR - NEAR)).clamp(0., 1.);
+        let scale = 1. - 0.5 * far;
+        let alpha = 1. - 0.6 * far;
+
+        let indicator_size = INDICATOR_SIZE * scale;
+        let edge = screen_edge(dir, size, INDICATOR_INSET);
+
+        style.left = Val::Px(edge.x - indicator_size / 2.);
+        style.top = Val::Px(edge.y - indicator_size / 2.);
+        style.width = Val::Px(indicator_size);
+        style.height = Val::Px(indicator_size);
+        *visibility = Visibility::Inherited;
+
+        let mut arrows = arrows.iter_many_mut(children);
+        while let Some((mut transform, mut arrow)) = arrows.fetch_next() {
+            transform.rotation = Quat::from_rotation_z(dir.x.atan2(dir.y));
+            arrow.color = indicator.kind.color().with_alpha(alpha);
+        }
+
+        let mut icons = icons.iter_many_mut(children);
+        while let Some(mut icon) = icons.fetch_next() {
+            let texture = image
+                .clone()
+                .unwrap_or_else(|| assets.load(indicator.kind.icon()));
+
+            if icon.texture != texture {
+                icon.texture = texture;
+            }
+            icon.color = Color::WHITE.with_alpha(alpha);
+        }
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_indicators)
+        .add_systems(
+            Update,
+            indicator_system.run_if(in_state(GameState::Playing)),
+        );
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{energy::Purchase, state::GameState, MainCamera};
+
+/// Gameplay reads the player's controls from here instead of the keyboard and mouse,
+/// so that runs can be recorded and replayed.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct PlayerInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub target: Option<Vec2>,
+    /// Whether atoms that are picked up wait to be placed by hand.
+    pub build_mode: bool,
+    pub place: bool,
+    pub buy: Option<Purchase>,
+}
+
+impl PlayerInput {
+    /// The direction to move in, not normalised.
+    pub fn movement(&self) -> Vec2 {
+        let mut movement = Vec2::ZERO;
+
+        if self.up {
+            movement.y += 1.;
+        }
+
+        if self.down {
+            movement.y -= 1.;
+        }
+
+        if self.right {
+            movement.x += 1.;
+        }
+
+        if self.left {
+            movement.x -= 1.;
+        }
+
+        movement
+    }
+}
+
+/// Whether build mode is on, kept apart from [`PlayerInput`]
+/// so a quick tap isn't missed between fixed ticks.
+#[derive(Resource, Default)]
+pub struct BuildModeToggle(pub bool);
+
+pub fn toggle_build_mode(keys: Res<ButtonInput<KeyCode>>, mut toggle: ResMut<BuildModeToggle>) {
+    if keys.just_pressed(KeyCode::KeyB) {
+        toggle.0 = !toggle.0;
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PendingPurchase(pub Option<Purchase>);
+
+pub fn read_purchase_keys(keys: Res<ButtonInput<KeyCode>>, mut pending: ResMut<PendingPurchase>) {
+    const HOTKEYS: [KeyCode; Purchase::ALL.len()] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+    ];
+
+    for (key, purchase) in HOTKEYS.into_iter().zip(Purchase::ALL) {
+        if keys.just_pressed(key) {
+            pending.0 = Some(purchase);
+        }
+    }
+}
+
+pub fn read_player_input(
+    mut input: ResMut<PlayerInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    toggle: Res<BuildModeToggle>,
+    mut pending: ResMut<PendingPurchase>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    input.up = keys.pressed(KeyCode::KeyW);
+    input.down = keys.pressed(KeyCode::KeyS);
+    input.left = keys.pressed(KeyCode::KeyA);
+    input.right = keys.pressed(KeyCode::KeyD);
+    input.build_mode = toggle.0;
+    input.place = mouse.pressed(MouseButton::Left);
+    input.buy = pending.0.take();
+
+    input.target = windows
+        .get_single()
+        .ok()
+        .zip(cameras.get_single().ok())
+        .and_then(|(window, (camera, camera_transform))| {
+            window
+                .cursor_position()
+                .and_then(|cursor_pos| camera.viewport_to_world_2d(camera_transform, cursor_pos))
+        });
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+pub fn reset_input(mut input: ResMut<PlayerInput>) {
+    *input = PlayerInput::default();
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<PlayerInput>()
+        .configure_sets(
+            FixedPreUpdate,
+            InputSet.run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnEnter(GameState::Playing), reset_input);
+}
+
+pub fn device_plugin(app: &mut App) {
+    app.init_resource::<BuildModeToggle>()
+        .init_resource::<PendingPurchase>()
+        .add_systems(Update, (toggle_build_mode, read_purchase_keys))
+        .add_systems(FixedPreUpdate, read_player_input.in_set(InputSet));
+}
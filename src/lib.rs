@@ -0,0 +1,91 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+pub mod movement;
+pub use movement::*;
+pub mod velocity;
+pub use velocity::Velocity;
+pub mod camera;
+pub use camera::MainCamera;
+pub mod time_to_live;
+pub use time_to_live::TimeToLive;
+pub mod player;
+pub mod shooting;
+pub use player::Player;
+pub mod boss;
+pub mod build;
+pub mod collision;
+pub mod death;
+pub mod decay;
+pub mod elements;
+pub mod enemy;
+pub mod energy;
+pub mod follow;
+pub mod headless;
+pub mod health;
+pub mod indicator;
+pub mod input;
+pub mod menu;
+pub mod molecule;
+pub mod powerup;
+pub mod radar;
+pub mod replay;
+pub mod rng;
+pub mod ron_asset;
+pub mod state;
+pub mod status;
+pub mod timer;
+pub mod utils;
+pub mod wave;
+
+use bevy::prelude::*;
+
+pub fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+pub fn gameplay_plugins(app: &mut App) {
+    app.add_plugins((
+        time_to_live::plugin,
+        velocity::plugin,
+        collision::plugin,
+        input::plugin,
+        replay::plugin,
+        movement::plugin,
+        shooting::plugin,
+        player::plugin,
+        elements::plugin,
+        molecule::plugin,
+        boss::plugin,
+        decay::plugin,
+        enemy::plugin,
+        wave::plugin,
+        energy::plugin,
+    ))
+    .add_plugins((
+        powerup::plugin,
+        timer::plugin,
+        state::plugin,
+        rng::plugin,
+        build::plugin,
+        status::plugin,
+    ));
+}
+
+pub fn presentation_plugins(app: &mut App) {
+    app.add_plugins((
+        camera::plugin,
+        input::device_plugin,
+        health::plugin,
+        powerup::hud_plugin,
+        indicator::plugin,
+        radar::plugin,
+        timer::hud_plugin,
+        wave::hud_plugin,
+        boss::hud_plugin,
+        build::hud_plugin,
+        energy::hud_plugin,
+        status::hud_plugin,
+        death::plugin,
+        menu::plugin,
+    ));
+}
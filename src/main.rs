@@ -1,31 +1,3 @@
-#![allow(clippy::type_complexity, clippy::too_many_arguments)]
-
-mod movement;
-pub use movement::*;
-mod velocity;
-pub use velocity::Velocity;
-mod camera;
-pub use camera::MainCamera;
-mod time_to_live;
-pub use time_to_live::TimeToLive;
-mod player;
-mod shooting;
-pub use player::Player;
-mod collision;
-mod death;
-mod elements;
-mod enemy;
-mod energy;
-mod follow;
-mod health;
-mod menu;
-mod molecule;
-mod powerup;
-mod state;
-mod timer;
-mod utils;
-mod wave;
-
 use bevy::{
     asset::AssetMetaCheck,
     audio::{PlaybackMode, Volume},
@@ -36,6 +8,7 @@ use bevy::{
     },
     window::WindowMode,
 };
+use gmtk_2024::{arg_value, gameplay_plugins, presentation_plugins, replay, rng};
 
 fn create_background(mut cmds: Commands, assets: ResMut<AssetServer>) {
     let texture = assets.load_with_settings("Background.png", |s| {
@@ -77,7 +50,23 @@ fn setup_music(mut cmds: Commands, assets: Res<AssetServer>) {
 fn main() {
     // std::env::set_var("RUST_BACKTRACE", "1");
 
-    App::new()
+    // `--seed <number>` plays the same run every time
+    let seed = arg_value("--seed").and_then(|seed| seed.parse().ok());
+
+    let mut app = App::new();
+
+    // `--record <path>` saves each run, and `--replay <path>` plays one back
+    if let Some(path) = arg_value("--record") {
+        app.insert_resource(replay::Recorder::new(path));
+    }
+
+    if let Some(path) = arg_value("--replay") {
+        let replay = replay::Replay::load(path.as_ref())
+            .unwrap_or_else(|err| panic!("Couldn't load replay {path}: {err}"));
+        app.insert_resource(replay::Playback::new(replay));
+    }
+
+    app
         // Enable ambiguity detection
         // Have to ignore the warning for time_system and event_update_system
         .configure_schedules(ScheduleBuildSettings {
@@ -106,22 +95,8 @@ fn main() {
                     ..Default::default()
                 }),
         )
-        .add_plugins(camera::plugin)
-        .add_plugins(time_to_live::plugin)
-        .add_plugins(velocity::plugin)
-        .add_plugins(movement::plugin)
-        .add_plugins(shooting::plugin)
-        .add_plugins(player::plugin)
-        .add_plugins(molecule::plugin)
-        .add_plugins(enemy::plugin)
-        .add_plugins(health::plugin)
-        .add_plugins(wave::plugin)
-        .add_plugins(energy::plugin)
-        .add_plugins(powerup::plugin)
-        .add_plugins(timer::plugin)
-        .add_plugins(state::plugin)
-        .add_plugins(death::plugin)
-        .add_plugins(menu::plugin)
+        .add_plugins((gameplay_plugins, presentation_plugins))
+        .insert_resource(rng::FixedSeed(seed))
         .add_systems(Startup, (create_background, setup_music))
         .run();
 }
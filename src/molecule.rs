@@ -1,13 +1,30 @@
 use bevy::prelude::*;
 
 use crate::{
-    elements::{Atom, ElementInfo},
-    enemy::Enemy,
+    boss::BossCore,
+    collision::{rebuild_spatial_grid, Collider, CollisionLayers, Layer},
+    decay::{Decayed, RadiationBurst},
+    elements::{Atom, ElementInfo, ElementTable, ATOM_RADIUS},
+    enemy::{Aim, Behaviour, Enemy},
     energy::Energy,
     health::Health,
-    Player,
+    player::Fragment,
+    rng::{GameRng, RngStream},
+    state::PlayingCleanup,
+    Player, Velocity,
 };
 
+/// Everything that sends [`BuildMolecule`] during a tick, in a fixed order,
+/// so the same seed and inputs always play out the same way.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuildMoleculeSet {
+    Waves,
+    Powerups,
+    Fragments,
+    Placing,
+    Damage,
+}
+
 #[derive(Event)]
 pub enum BuildMolecule {
     Create {
@@ -16,49 +33,224 @@ pub enum BuildMolecule {
     Add {
         target: Entity,
         element: ElementInfo,
+        /// Where the atom touched the molecule, so it can be attached there,
+        /// otherwise it goes as close to the middle as it can.
+        near: Option<Vec2>,
     },
     RemoveAtom {
         target: Entity,
         atom: Entity,
     },
+    Transmute {
+        target: Entity,
+        atom: Entity,
+        element: ElementInfo,
+    },
+}
+
+pub const BOND_LENGTH: f32 = ATOM_RADIUS * 2.;
+
+/// The six neighbours of a cell on the hex lattice, in axial coordinates.
+const NEIGHBOURS: [IVec2; 6] = [
+    IVec2::new(1, 0),
+    IVec2::new(1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, 1),
+    IVec2::new(0, 1),
+];
+
+pub fn cell_position(cell: IVec2) -> Vec2 {
+    (cell.x as f32 * Vec2::X + cell.y as f32 * Vec2::new(0.5, 3f32.sqrt() / 2.)) * BOND_LENGTH
 }
 
 #[derive(Component)]
 pub struct Molecule {
     pub elements: Vec<ElementInfo>,
+    /// Where each atom sits on a hex lattice, so molecules stay packed however big they get.
+    pub cells: Vec<IVec2>,
+    pub bonds: Vec<(usize, usize)>,
 }
 
 impl Molecule {
-    pub fn collision_radius(&self) -> f32 {
-        (match self.elements.len() {
-            1 => 32.,
-            2 => 56.,
-            4 => 64.,
-            n => n as f32 * 24.,
-        }) + 24.
+    pub fn new(elements: Vec<ElementInfo>) -> Self {
+        let mut molecule = Self {
+            elements: Vec::new(),
+            cells: Vec::new(),
+            bonds: Vec::new(),
+        };
+
+        for element in elements {
+            molecule.push(element, Vec2::ZERO);
+        }
+
+        molecule
+    }
+
+    pub fn threat(&self) -> f32 {
+        self.elements.len() as f32
+    }
+
+    pub fn position(&self, index: usize) -> Vec2 {
+        cell_position(self.cells[index])
+    }
+
+    pub fn neighbours(&self, index: usize) -> Vec<usize> {
+        self.bonds
+            .iter()
+            .filter_map(|&(a, b)| match index {
+                _ if a == index => Some(b),
+                _ if b == index => Some(a),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn free_cell(&self, near: Vec2) -> IVec2 {
+        self.cells
+            .iter()
+            .flat_map(|&cell| NEIGHBOURS.map(|offset| cell + offset))
+            .filter(|cell| !self.cells.contains(cell))
+            .min_by(|&a, &b| {
+                cell_position(a)
+                    .distance_squared(near)
+                    .total_cmp(&cell_position(b).distance_squared(near))
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn push(&mut self, element: ElementInfo, near: Vec2) {
+        let cell = self.free_cell(near);
+
+        let index = self.elements.len();
+
+        for offset in NEIGHBOURS {
+            if let Some(neighbour) = self.cells.iter().position(|&c| c == cell + offset) {
+                self.bonds.push((neighbour, index));
+            }
+        }
+
+        self.elements.push(element);
+        self.cells.push(cell);
+    }
+
+    /// The groups of atoms that would still be bonded together without the atom at `index`,
+    /// as sorted indices.
+    pub fn parts_without(&self, index: usize) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.elements.len()];
+        seen[index] = true;
+
+        let mut parts = Vec::new();
+
+        for start in 0..self.elements.len() {
+            if seen[start] {
+                continue;
+            }
+
+            seen[start] = true;
+            let mut part = vec![start];
+            let mut stack = vec![start];
+
+            while let Some(i) = stack.pop() {
+                for j in self.neighbours(i) {
+                    if !seen[j] {
+                        seen[j] = true;
+                        part.push(j);
+                        stack.push(j);
+                    }
+                }
+            }
+
+            part.sort();
+            parts.push(part);
+        }
+
+        parts
+    }
+
+    /// Just the atoms at `indices`, which must be sorted, and the bonds between them.
+    pub fn subset(&self, indices: &[usize]) -> Molecule {
+        let new_index = |old| indices.binary_search(&old).ok();
+
+        Molecule {
+            elements: indices.iter().map(|&i| self.elements[i]).collect(),
+            cells: indices.iter().map(|&i| self.cells[i]).collect(),
+            bonds: self
+                .bonds
+                .iter()
+                .filter_map(|&(a, b)| Some((new_index(a)?, new_index(b)?)))
+                .collect(),
+        }
+    }
+
+    /// Move every atom so the first one is in the middle,
+    /// returning where the first one was.
+    pub fn recentre(&mut self) -> Vec2 {
+        let Some(&origin) = self.cells.first() else {
+            return Vec2::ZERO;
+        };
+
+        for cell in &mut self.cells {
+            *cell -= origin;
+        }
+
+        cell_position(origin)
     }
 }
 
-fn create_polygon(points: usize) -> Vec<Vec2> {
-    let start_point = match points {
-        1 => Vec2::new(0., 0.),
-        2 => Vec2::new(-56., 0.),
-        4 => Vec2::new(64., 64.),
-        _ => Vec2::new(0., points as f32 * 24.),
-    };
+const SPLIT_SPEED: f32 = 4.;
+
+fn layout(
+    atoms: &[Entity],
+    molecule: &Molecule,
+    transforms: &mut Query<&mut Transform, With<Parent>>,
+) {
+    for (i, &atom) in atoms.iter().enumerate() {
+        if let Ok(mut transform) = transforms.get_mut(atom) {
+            let position = molecule.position(i);
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    }
+}
 
-    (0..points)
-        .map(|i| Rot2::degrees(i as f32 * -360. / points as f32) * start_point)
+/// The atoms of a molecule without any removed or moved earlier this tick,
+/// so they line up with its elements again.
+fn live_atoms(children: &Children, gone: &[Entity]) -> Vec<Entity> {
+    children
+        .iter()
+        .copied()
+        .filter(|child| !gone.contains(child))
         .collect()
 }
 
 pub fn build_molecules_system(
     mut events: EventReader<BuildMolecule>,
     assets: Res<AssetServer>,
+    table: Res<ElementTable>,
+    mut rng: ResMut<GameRng>,
     mut cmds: Commands,
     mut molecules: Query<(Entity, &mut Molecule, Option<&Player>, Option<&Children>)>,
+    owners: Query<
+        (
+            &Transform,
+            Option<&Velocity>,
+            Option<&Enemy>,
+            Option<&Behaviour>,
+            Option<&Aim>,
+            Option<&CollisionLayers>,
+        ),
+        Without<Parent>,
+    >,
+    cores: Query<(), With<BossCore>>,
     mut child_transforms: Query<&mut Transform, With<Parent>>,
+    healths: Query<&Health>,
+    mut gone: Local<Vec<Entity>>,
+    mut fragments: Local<Vec<(Entity, Molecule, Vec<Entity>)>>,
 ) {
+    let rng = rng.stream(RngStream::Shooters);
+    gone.clear();
+
     for event in events.read() {
         match *event {
             BuildMolecule::Create { target } => {
@@ -67,104 +259,345 @@ pub fn build_molecules_system(
                         return;
                     };
 
-                    let offsets = create_polygon(molecule.elements.len());
-
                     molecule
                         .elements
                         .iter()
                         .enumerate()
                         .for_each(|(i, element)| {
-                            element.build(parent, &assets, offsets[i], player.is_some());
+                            element.build(
+                                parent,
+                                &assets,
+                                &table,
+                                rng,
+                                molecule.position(i),
+                                player.is_some(),
+                            );
                         });
                 });
             }
-            BuildMolecule::Add { target, element } => {
-                let Ok((_, mut molecule, player, Some(old_children))) = molecules.get_mut(target)
-                else {
-                    return;
+            BuildMolecule::Add {
+                target,
+                element,
+                near,
+            } => {
+                let Ok((_, mut molecule, player, _)) = molecules.get_mut(target) else {
+                    continue;
                 };
 
-                molecule.elements.push(element);
-
-                let offsets = create_polygon(molecule.elements.len());
+                let near = match (near, owners.get(target)) {
+                    (Some(near), Ok((owner, ..))) => owner
+                        .compute_affine()
+                        .inverse()
+                        .transform_point3(near.extend(0.))
+                        .xy(),
+                    _ => Vec2::ZERO,
+                };
 
-                for (i, child) in old_children.iter().enumerate() {
-                    let pos = &mut child_transforms.get_mut(*child).unwrap().translation;
-                    let offset = offsets[i];
-                    pos.x = offset.x;
-                    pos.y = offset.y;
-                }
+                molecule.push(element, near);
+                let position = molecule.position(molecule.elements.len() - 1);
 
                 cmds.entity(target).with_children(|parent| {
-                    element.build(parent, &assets, *offsets.last().unwrap(), player.is_some());
+                    element.build(parent, &assets, &table, rng, position, player.is_some());
                 });
             }
             BuildMolecule::RemoveAtom { target, atom } => {
-                let Ok((entity, mut molecule, _, Some(old_children))) = molecules.get_mut(target)
+                let Ok((entity, mut molecule, player, Some(children))) = molecules.get_mut(target)
                 else {
-                    return;
+                    continue;
                 };
 
-                if let Some((mut index, _)) = old_children
+                let children = live_atoms(children, &gone);
+
+                let Some(index) = children.iter().position(|child| *child == atom) else {
+                    continue;
+                };
+
+                // If this is the only child
+                if molecule.elements.len() == 1 {
+                    cmds.entity(entity).despawn_recursive();
+                    gone.push(atom);
+                    continue;
+                }
+
+                let mut atom_cmds = cmds.entity(atom);
+                atom_cmds.remove_parent();
+                atom_cmds.despawn_recursive();
+                gone.push(atom);
+
+                // Atoms added this tick haven't been spawned yet, so can't be split off
+                let mut parts = if children.len() == molecule.elements.len() {
+                    molecule.parts_without(index)
+                } else {
+                    vec![(0..molecule.elements.len())
+                        .filter(|&i| i != index)
+                        .collect()]
+                };
+
+                // A boss keeps its core, anything else keeps its biggest part
+                let kept = parts
                     .iter()
-                    .enumerate()
-                    .find(|(_, child)| **child == atom)
-                {
-                    // If this is the only child
-                    if molecule.elements.len() == 1 {
-                        cmds.entity(entity).despawn_recursive();
-                        continue;
+                    .position(|part| {
+                        part.iter()
+                            .any(|&i| children.get(i).is_some_and(|&c| cores.contains(c)))
+                    })
+                    .or_else(|| (0..parts.len()).max_by_key(|&i| parts[i].len()))
+                    .unwrap_or_default();
+                let kept = parts.swap_remove(kept);
+
+                let Ok((owner, velocity, enemy, behaviour, aim, layers)) = owners.get(entity)
+                else {
+                    continue;
+                };
+
+                for part in parts {
+                    let atoms: Vec<_> = part.iter().map(|&i| children[i]).collect();
+
+                    let mut split = molecule.subset(&part);
+                    let origin = split.recentre();
+
+                    let away = (part.iter().map(|&i| molecule.position(i)).sum::<Vec2>()
+                        / part.len() as f32)
+                        .normalize_or_zero();
+                    let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.velocity);
+
+                    layout(&atoms, &split, &mut child_transforms);
+
+                    let fragment = cmds
+                        .spawn((
+                            SpatialBundle::from_transform(Transform::from_translation(
+                                owner.transform_point(origin.extend(0.)),
+                            )),
+                            Velocity {
+                                // Fly apart from what's left
+                                velocity: velocity + away.extend(0.) * SPLIT_SPEED,
+                                drag: 0.04,
+                                max_speed: None,
+                            },
+                        ))
+                        .id();
+
+                    if player.is_some() {
+                        cmds.entity(fragment).insert((
+                            Fragment,
+                            CollisionLayers::new(Layer::NEUTRAL, Layer::PLAYER),
+                            PlayingCleanup,
+                        ));
                     }
 
-                    // If removal is invalid
-                    if index >= molecule.elements.len() {
-                        index = molecule.elements.len() - 1;
+                    if let Some(enemy) = enemy {
+                        cmds.entity(fragment).insert(enemy.clone());
                     }
 
-                    molecule.elements.remove(index);
-                    let mut atom_cmds = cmds.entity(atom);
-                    atom_cmds.remove_parent();
-                    atom_cmds.despawn_recursive();
+                    if let Some(&behaviour) = behaviour {
+                        cmds.entity(fragment).insert(behaviour);
+                    }
 
-                    let offsets = create_polygon(molecule.elements.len());
+                    if let Some(&aim) = aim {
+                        cmds.entity(fragment).insert(aim);
+                    }
 
-                    for (i, child) in old_children.iter().enumerate().filter(|(i, _)| *i != index) {
-                        let pos = &mut child_transforms.get_mut(*child).unwrap().translation;
-                        let offset = offsets
-                            .get(if i > index { i - 1 } else { i })
-                            .unwrap_or_else(|| &offsets[offsets.len() - 1]);
-                        pos.x = offset.x;
-                        pos.y = offset.y;
+                    if let (Some(&layers), None) = (layers, player) {
+                        cmds.entity(fragment).insert(layers);
                     }
+
+                    cmds.entity(fragment).push_children(&atoms);
+                    gone.extend(&atoms);
+
+                    // Inserted once every event is handled, in case its atoms change too
+                    fragments.push((fragment, split, atoms));
                 }
+
+                // What's left stays where it is
+                *molecule = molecule.subset(&kept);
+            }
+            BuildMolecule::Transmute {
+                target,
+                atom,
+                element,
+            } => {
+                let split = fragments.iter_mut().find_map(|(_, split, atoms)| {
+                    let index = atoms.iter().position(|child| *child == atom)?;
+                    Some((split, index))
+                });
+
+                if let Some((split, index)) = split {
+                    split.elements[index] = element;
+                } else {
+                    let Ok((_, mut molecule, _, Some(children))) = molecules.get_mut(target) else {
+                        continue;
+                    };
+
+                    let Some(index) = live_atoms(children, &gone)
+                        .iter()
+                        .position(|child| *child == atom)
+                    else {
+                        continue;
+                    };
+
+                    if let Some(old) = molecule.elements.get_mut(index) {
+                        *old = element;
+                    }
+                }
+
+                // A fresh atom of the lighter element, still behind any shield
+                let (element, mut health, shooter) = element.components(&table, rng);
+                health.shield = healths.get(atom).map_or(0., |old| old.shield);
+
+                cmds.entity(atom)
+                    .insert((
+                        element,
+                        health,
+                        shooter,
+                        assets.load::<Image>(&table[element].image_path),
+                    ))
+                    .remove::<Decayed>();
             }
         }
     }
+
+    for (fragment, split, _) in fragments.drain(..) {
+        cmds.entity(fragment).insert(split);
+    }
 }
 
 pub fn molecule_health_system(
     enemies: Query<&Enemy>,
-    query: Query<(Entity, &Health, &Parent), With<Atom>>,
+    molecules: Query<&Molecule>,
+    cores: Query<(), With<BossCore>>,
+    mut query: Query<
+        (
+            Entity,
+            &ElementInfo,
+            &mut Health,
+            &Parent,
+            &GlobalTransform,
+            Has<Decayed>,
+        ),
+        With<Atom>,
+    >,
+    table: Res<ElementTable>,
     mut build_molecule_event: EventWriter<BuildMolecule>,
+    mut burst_event: EventWriter<RadiationBurst>,
     mut energy: ResMut<Energy>,
 ) {
-    for (entity, health, parent) in query.iter() {
+    for (entity, &element, mut health, parent, transform, decayed) in &mut query {
         if health.health <= 0. {
-            build_molecule_event.send(BuildMolecule::RemoveAtom {
-                target: parent.get(),
-                atom: entity,
+            // A boss's core holds on until the rest of the boss is gone
+            if cores.contains(entity)
+                && molecules
+                    .get(parent.get())
+                    .is_ok_and(|molecule| molecule.elements.len() > 1)
+            {
+                health.health = 1.;
+                continue;
+            }
+
+            // Radioactive atoms burst, and may leave a lighter element behind
+            let transmute = table[element].decay.and_then(|decay| {
+                burst_event.send(RadiationBurst {
+                    origin: transform.translation().xy(),
+                    radius: decay.burst_radius,
+                    damage: decay.burst_damage,
+                    source: parent.get(),
+                });
+
+                decay.transmute.filter(|_| decayed)
             });
 
-            if enemies.contains(parent.get()) {
-                energy.0 += 10.;
+            match transmute {
+                Some(element) => {
+                    build_molecule_event.send(BuildMolecule::Transmute {
+                        target: parent.get(),
+                        atom: entity,
+                        element,
+                    });
+                }
+                None => {
+                    build_molecule_event.send(BuildMolecule::RemoveAtom {
+                        target: parent.get(),
+                        atom: entity,
+                    });
+
+                    if enemies.contains(parent.get()) {
+                        energy.0 += 10.;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn bond_health_system(
+    table: Res<ElementTable>,
+    molecules: Query<(&Molecule, &Children)>,
+    mut atoms: Query<(&ElementInfo, &mut Health), With<Atom>>,
+) {
+    for (molecule, children) in &molecules {
+        for (i, child) in children.iter().enumerate() {
+            let Ok((&element, mut health)) = atoms.get_mut(*child) else {
+                continue;
+            };
+
+            let bonus: f32 = molecule
+                .neighbours(i)
+                .into_iter()
+                .filter_map(|j| molecule.elements.get(j))
+                .map(|&neighbour| table[neighbour].bond_health)
+                .sum();
+
+            let max_health = table[element].max_health + bonus;
+
+            if health.max_health != max_health {
+                // Keep the same percentage of health
+                let percent = health.health / health.max_health;
+                health.max_health = max_health;
+                health.health = percent * max_health;
             }
         }
     }
 }
 
+pub fn molecule_collider_system(
+    mut cmds: Commands,
+    molecules: Query<
+        (Entity, &Children),
+        (With<Molecule>, Or<(Changed<Children>, Changed<Molecule>)>),
+    >,
+    atoms: Query<&Transform, With<Atom>>,
+) {
+    for (entity, children) in &molecules {
+        let circles = atoms
+            .iter_many(children)
+            .map(|transform| (transform.translation.xy(), ATOM_RADIUS))
+            .collect();
+
+        cmds.entity(entity).insert(Collider::Compound(circles));
+    }
+}
+
 pub fn plugin(app: &mut App) {
-    app.add_event::<BuildMolecule>().add_systems(
-        Update,
-        (molecule_health_system, build_molecules_system).chain(),
-    );
+    app.add_event::<BuildMolecule>()
+        .configure_sets(
+            FixedUpdate,
+            (
+                BuildMoleculeSet::Waves,
+                BuildMoleculeSet::Powerups,
+                BuildMoleculeSet::Fragments,
+                BuildMoleculeSet::Placing,
+                BuildMoleculeSet::Damage,
+            )
+                .chain()
+                .before(build_molecules_system),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                molecule_health_system.in_set(BuildMoleculeSet::Damage),
+                (build_molecules_system, bond_health_system).chain(),
+            ),
+        )
+        .add_systems(
+            FixedPreUpdate,
+            molecule_collider_system.before(rebuild_spatial_grid),
+        );
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::Velocity;
+use crate::{input::PlayerInput, Velocity};
 
 #[derive(Component)]
 pub struct Movement {
@@ -8,33 +8,12 @@ pub struct Movement {
     pub max_velocity: f32,
 }
 
-pub fn movement_system(
-    mut query: Query<(&mut Velocity, &Movement)>,
-    keys: Res<ButtonInput<KeyCode>>,
-) {
+pub fn movement_system(mut query: Query<(&mut Velocity, &Movement)>, input: Res<PlayerInput>) {
     let Ok((mut velocity, movement)) = query.get_single_mut() else {
         return;
     };
 
-    let mut offset = Vec2::ZERO;
-
-    if keys.pressed(KeyCode::KeyW) {
-        offset += Vec2::new(0., movement.acceleration);
-    }
-
-    if keys.pressed(KeyCode::KeyS) {
-        offset += Vec2::new(0., -movement.acceleration);
-    }
-
-    if keys.pressed(KeyCode::KeyD) {
-        offset += Vec2::new(movement.acceleration, 0.);
-    }
-
-    if keys.pressed(KeyCode::KeyA) {
-        offset += Vec2::new(-movement.acceleration, 0.);
-    }
-
-    offset = offset.clamp_length_max(movement.acceleration);
+    let offset = (input.movement() * movement.acceleration).clamp_length_max(movement.acceleration);
 
     velocity.velocity += Vec3::new(offset.x, offset.y, 0.);
     velocity.velocity = velocity.velocity.clamp_length_max(movement.max_velocity);
@@ -1,15 +1,21 @@
 use bevy::prelude::*;
 
 use crate::{
-    elements::ElementInfo,
-    molecule::{BuildMolecule, Molecule},
+    collision::{collision_system, CollisionEvent, CollisionLayers, Layer},
+    elements::{Atom, ElementInfo},
+    molecule::{BuildMolecule, BuildMoleculeSet, Molecule},
     state::GameState,
+    status::StatusEffects,
     Movement, Velocity,
 };
 
 #[derive(Component, Clone)]
 pub struct Player;
 
+/// A piece broken off the player's molecule, which can be picked up again.
+#[derive(Component, Clone)]
+pub struct Fragment;
+
 pub fn create_player(mut cmds: Commands, mut build_molecule_event: EventWriter<BuildMolecule>) {
     let id = cmds
         .spawn((
@@ -24,15 +30,53 @@ pub fn create_player(mut cmds: Commands, mut build_molecule_event: EventWriter<B
                 max_speed: None,
             },
             Player,
-            Molecule {
-                elements: vec![ElementInfo::Thorium],
-            },
+            StatusEffects::default(),
+            CollisionLayers::new(
+                Layer::PLAYER,
+                Layer::ENEMY | Layer::POWERUP | Layer::NEUTRAL,
+            ),
+            Molecule::new(vec![ElementInfo::Thorium]),
         ))
         .id();
 
     build_molecule_event.send(BuildMolecule::Create { target: id });
 }
 
+pub fn fragment_player_collision_system(
+    mut events: EventReader<CollisionEvent<Fragment, Player>>,
+    fragments: Query<(&Molecule, &Children), With<Fragment>>,
+    atoms: Query<&GlobalTransform, With<Atom>>,
+    mut build_molecule_event: EventWriter<BuildMolecule>,
+    mut cmds: Commands,
+) {
+    for event in events.read() {
+        let Ok((fragment, children)) = fragments.get(event.a_id) else {
+            continue;
+        };
+
+        // Each atom goes back on where it is now
+        for (&element, atom) in fragment.elements.iter().zip(atoms.iter_many(children)) {
+            build_molecule_event.send(BuildMolecule::Add {
+                target: event.b_id,
+                element,
+                near: Some(atom.translation().xy()),
+            });
+        }
+
+        cmds.entity(event.a_id).despawn_recursive();
+    }
+}
+
 pub fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::Playing), create_player);
+    app.add_event::<CollisionEvent<Fragment, Player>>()
+        .add_systems(OnEnter(GameState::Playing), create_player)
+        .add_systems(
+            FixedUpdate,
+            (
+                collision_system::<Fragment, Player>,
+                fragment_player_collision_system,
+            )
+                .chain()
+                .in_set(BuildMoleculeSet::Fragments),
+        );
 }
@@ -1,25 +1,96 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 use rand::Rng;
 
 use crate::{
-    collision::{collision_system, CollisionEvent},
-    elements::ElementInfo,
-    energy::Energy,
-    molecule::{BuildMolecule, Molecule},
+    build::BuildQueue,
+    collision::{collision_system, Collider, CollisionEvent, CollisionLayers, Layer},
+    elements::{Atom, ElementInfo, ElementTable},
+    enemy::Enemy,
+    energy::{powerup_cost, Energy},
+    health::Health,
+    input::PlayerInput,
+    molecule::{BuildMolecule, BuildMoleculeSet, Molecule},
+    rng::{GameRng, RngStream},
+    status::{repair, shield, StatusEffect, StatusEffects},
+    time_to_live::time_to_live,
     utils::random_in_donut,
-    Player,
+    Player, TimeToLive,
 };
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
 pub enum Powerup {
     AddAtom(ElementInfo),
+    Repair,
+    FireRate,
+    Shield,
+    Magnet,
+    Energy,
+}
+
+impl Powerup {
+    /// The powerups that aren't atoms, which spawn now and then instead of one.
+    pub const SPECIAL: [Powerup; 5] = [
+        Powerup::Repair,
+        Powerup::FireRate,
+        Powerup::Shield,
+        Powerup::Magnet,
+        Powerup::Energy,
+    ];
+
+    pub fn image_path<'a>(&self, table: &'a ElementTable) -> &'a str {
+        match self {
+            Powerup::AddAtom(element) => &table[*element].image_path,
+            Powerup::Repair => "PowerupRepair.png",
+            Powerup::FireRate => "PowerupFireRate.png",
+            Powerup::Shield => "PowerupShield.png",
+            Powerup::Magnet => "PowerupMagnet.png",
+            Powerup::Energy => "PowerupEnergy.png",
+        }
+    }
 }
 
+pub const SPECIAL_POWERUP_CHANCE: f64 = 0.25;
+
+pub const FIRE_RATE_BOOST: f32 = 2.;
+
+pub const FIRE_RATE_DURATION: f32 = 8.;
+
+pub const MAGNET_DURATION: f32 = 15.;
+
+/// How much energy an energy powerup is worth, in random powerups.
+pub const ENERGY_CACHE: f32 = 2.;
+
+pub const POWERUP_LIFETIME: Duration = Duration::from_secs(30);
+
+pub const POWERUP_BLINK_TIME: Duration = Duration::from_secs(5);
+
+/// How many powerups there can be at once before random ones stop spawning.
+pub const MAX_POWERUPS: usize = 8;
+
+/// How much of a powerup's cost comes back if nobody picks it up.
+pub const POWERUP_REFUND: f32 = 0.5;
+
+#[derive(Component)]
+pub struct Refund(pub f32);
+
+/// How many more powerups enemies can absorb this wave.
+#[derive(Resource, Default)]
+pub struct EnemyGrowth {
+    pub remaining: usize,
+}
+
+pub const FORAGE_RANGE: f32 = 1000.;
+
 pub fn spawn_powerup_system(
     mut cmds: Commands,
     players: Query<(&Transform, &Molecule), With<Player>>,
+    powerups: Query<(), With<Powerup>>,
     mut energy: ResMut<Energy>,
     assets: Res<AssetServer>,
+    table: Res<ElementTable>,
+    mut rng: ResMut<GameRng>,
 ) {
     let Ok((player, molecule)) = players.get_single() else {
         return;
@@ -27,172 +98,229 @@ pub fn spawn_powerup_system(
     let center = player.translation.xy();
 
     let player_size = molecule.elements.len();
-    let needed_energy = -30. + 50. * (player_size as f32).sqrt();
+    let needed_energy = powerup_cost(player_size);
 
-    if energy.0 >= needed_energy {
+    // Save up while there are plenty of powerups lying around
+    if energy.0 >= needed_energy && powerups.iter().len() < MAX_POWERUPS {
         energy.0 -= needed_energy;
 
-        let element = {
-            let mut rng = rand::thread_rng();
+        let rng = rng.stream(RngStream::Powerups);
+
+        let powerup = if rng.gen_bool(SPECIAL_POWERUP_CHANCE) {
+            Powerup::SPECIAL[rng.gen_range(0..Powerup::SPECIAL.len())]
+        } else {
             let all = match player_size {
                 count if count <= 3 => vec![
                     ElementInfo::Iron,
                     ElementInfo::Thorium,
                     ElementInfo::Thorium,
+                    ElementInfo::Carbon,
+                ],
+                count if count <= 6 => vec![
+                    ElementInfo::Iron,
+                    ElementInfo::Thorium,
+                    ElementInfo::Radium,
+                    ElementInfo::Carbon,
                 ],
-                count if count <= 6 => {
-                    vec![ElementInfo::Iron, ElementInfo::Thorium, ElementInfo::Radium]
-                }
                 _ => vec![
                     ElementInfo::Iron,
                     ElementInfo::Thorium,
                     ElementInfo::Radium,
                     ElementInfo::Radium,
+                    ElementInfo::Carbon,
                 ],
             };
             let index = rng.gen_range(0..all.len());
-            all[index]
+            Powerup::AddAtom(all[index])
         };
 
-        cmds.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::linear_rgb(1., 1., 1.),
-                    custom_size: Some(Vec2::new(64., 64.)),
-                    ..Default::default()
-                },
-                transform: Transform::from_translation(
-                    (center + random_in_donut(600., 2000.)).extend(0.),
-                ),
-                texture: assets.load(element.image_path()),
-                ..Default::default()
-            },
-            Powerup::AddAtom(element),
-        ));
+        spawn_powerup(
+            &mut cmds,
+            &assets,
+            &table,
+            powerup,
+            center + random_in_donut(rng, 600., 2000.),
+            needed_energy,
+        );
     }
 }
 
-#[derive(Component)]
-pub struct PowerupArrow;
-
-pub fn setup_powerup_arrow(mut cmds: Commands, assets: Res<AssetServer>) {
+pub fn spawn_powerup(
+    cmds: &mut Commands,
+    assets: &AssetServer,
+    table: &ElementTable,
+    powerup: Powerup,
+    position: Vec2,
+    cost: f32,
+) -> Entity {
     cmds.spawn((
-        ImageBundle {
-            style: Style {
-                left: Val::Percent(50.),
-                top: Val::Px(0.),
-                width: Val::Px(128.),
-                height: Val::Px(128.),
-                ..Default::default()
-            },
-            image: UiImage {
-                texture: assets.load("Arrow.png"),
-                color: Color::linear_rgba(1., 1., 1., 0.1),
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::linear_rgb(1., 1., 1.),
+                custom_size: Some(Vec2::new(64., 64.)),
                 ..Default::default()
             },
+            transform: Transform::from_translation(position.extend(0.)),
+            texture: assets.load(powerup.image_path(table).to_owned()),
             ..Default::default()
         },
-        PowerupArrow,
-    ));
+        Collider::Circle { radius: 32. },
+        CollisionLayers::new(Layer::POWERUP, Layer::PLAYER | Layer::ENEMY),
+        powerup,
+        TimeToLive::new(POWERUP_LIFETIME),
+        Refund(cost * POWERUP_REFUND),
+    ))
+    .id()
 }
 
-pub fn powerup_arrow_system(
-    powerups: Query<(&Transform, &ViewVisibility), (With<Powerup>, Without<PowerupArrow>)>,
-    players: Query<&Transform, (With<Player>, Without<PowerupArrow>)>,
-    mut arrows: Query<(&mut Transform, &mut Style, &mut UiImage), With<PowerupArrow>>,
-    windows: Query<&Window>,
-    mut last_delta: Local<Vec2>,
+pub fn powerup_refund_system(
+    time: Res<Time>,
+    powerups: Query<(&TimeToLive, &Refund), With<Powerup>>,
+    mut energy: ResMut<Energy>,
 ) {
-    let Ok((mut arrow_transform, mut arrow_style, mut arrow_image)) = arrows.get_single_mut()
-    else {
-        return;
-    };
-
-    let Ok(player) = players.get_single() else {
-        arrow_image.color.set_alpha(0.);
-        return;
-    };
-    let player = player.translation.xy();
-
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-
-    let image_padding = 64.;
-    let padding = 64.;
-
-    let width = window.width() - image_padding * 2. - padding * 2.;
-    let height = window.height() - image_padding * 2. - padding * 2.;
-
-    // Get the closest powerup that isn't visible
-    let Some((powerup, _)) = powerups
-        .iter()
-        .filter(|(_, is_visible)| !is_visible.get())
-        .min_by(|(a, _), (b, _)| {
-            (a.translation.xy() - player)
-                .length()
-                .total_cmp(&(b.translation.xy() - player).length())
-        })
-    else {
-        arrow_image.color = Color::linear_rgba(1., 1., 1., arrow_image.color.alpha().lerp(0., 0.1));
-        return;
-    };
+    for (ttl, refund) in &powerups {
+        if ttl.remaining() <= time.delta() {
+            energy.0 += refund.0;
+        }
+    }
+}
 
-    let delta = (powerup.translation.xy() - player).normalize();
-    let delta = if arrow_image.color.alpha() <= 0.01 {
-        delta
-    } else {
-        last_delta.lerp(delta, 0.05)
-    };
-    *last_delta = delta;
+pub fn powerup_blink_system(mut powerups: Query<(&TimeToLive, &mut Sprite), With<Powerup>>) {
+    for (ttl, mut sprite) in &mut powerups {
+        let remaining = ttl.remaining();
 
-    arrow_image.color = Color::linear_rgba(1., 1., 1., arrow_image.color.alpha().lerp(1., 0.04));
+        let alpha = if remaining < POWERUP_BLINK_TIME {
+            let left = remaining.as_secs_f32() / POWERUP_BLINK_TIME.as_secs_f32();
+            // Two blinks a second to start with, up to eight
+            let blinks = 2. + 6. * (1. - left);
 
-    let abs_delta = delta.abs();
+            if (remaining.as_secs_f32() * blinks).fract() < 0.5 {
+                0.2
+            } else {
+                1.
+            }
+        } else {
+            1.
+        };
 
-    let dx_by_width = 0.5 * width / abs_delta.x;
-    let dy_by_height = 0.5 * height / abs_delta.y;
+        sprite.color.set_alpha(alpha);
+    }
+}
 
-    let mut delta_scaled = delta * dx_by_width.min(dy_by_height);
-    delta_scaled.y *= -1.;
+pub fn powerup_player_collision_system(
+    mut events: EventReader<CollisionEvent<Powerup, Player>>,
+    powerups: Query<&Transform, With<Powerup>>,
+    mut players: Query<(&Molecule, &Children, &mut StatusEffects), With<Player>>,
+    mut healths: Query<&mut Health, With<Atom>>,
+    input: Res<PlayerInput>,
+    mut queue: ResMut<BuildQueue>,
+    mut energy: ResMut<Energy>,
+    mut build_molecule_event: EventWriter<BuildMolecule>,
+    mut cmds: Commands,
+) {
+    for event in events.read() {
+        let near = powerups
+            .get(event.a_id)
+            .ok()
+            .map(|transform| transform.translation.xy());
 
-    let screen_coords = delta_scaled + Vec2::new(width / 2. + padding, height / 2. + padding);
+        match event.a_comp {
+            // Let the player choose where it goes
+            Powerup::AddAtom(element) if input.build_mode => queue.0.push_back(element),
+            Powerup::AddAtom(element) => {
+                build_molecule_event.send(BuildMolecule::Add {
+                    target: event.b_id,
+                    element,
+                    near,
+                });
+            }
+            powerup => {
+                let Ok((molecule, children, mut effects)) = players.get_mut(event.b_id) else {
+                    continue;
+                };
 
-    arrow_style.left = Val::Px(screen_coords.x);
-    arrow_style.top = Val::Px(screen_coords.y);
+                match powerup {
+                    Powerup::Repair => repair(children, &mut healths),
+                    Powerup::FireRate => {
+                        effects.add(StatusEffect::FireRate(FIRE_RATE_BOOST), FIRE_RATE_DURATION)
+                    }
+                    Powerup::Shield => shield(children, &mut healths, &mut effects),
+                    Powerup::Magnet => effects.add(StatusEffect::Magnet, MAGNET_DURATION),
+                    Powerup::Energy => {
+                        energy.0 += ENERGY_CACHE * powerup_cost(molecule.elements.len())
+                    }
+                    Powerup::AddAtom(_) => {}
+                }
+            }
+        };
 
-    let rotation = delta.x.atan2(delta.y);
-    arrow_transform.rotation = Quat::from_rotation_z(rotation);
+        cmds.entity(event.a_id).despawn();
+    }
 }
 
-pub fn powerup_player_collision_system(
-    mut events: EventReader<CollisionEvent<Powerup, Player>>,
+pub fn powerup_enemy_collision_system(
+    mut events: EventReader<CollisionEvent<Powerup, Enemy>>,
+    powerups: Query<&Transform, With<Powerup>>,
+    mut growth: ResMut<EnemyGrowth>,
     mut build_molecule_event: EventWriter<BuildMolecule>,
     mut cmds: Commands,
+    mut absorbed: Local<Vec<Entity>>,
 ) {
+    absorbed.clear();
+
     for event in events.read() {
-        match event.a_comp {
-            Powerup::AddAtom(element) => build_molecule_event.send(BuildMolecule::Add {
-                target: event.b_id,
-                element,
-            }),
+        // The player or another enemy might have got there first
+        if growth.remaining == 0 || !powerups.contains(event.a_id) || absorbed.contains(&event.a_id)
+        {
+            continue;
+        }
+
+        // Enemies only have a use for atoms
+        let Powerup::AddAtom(element) = event.a_comp else {
+            continue;
         };
 
+        let near = powerups
+            .get(event.a_id)
+            .ok()
+            .map(|transform| transform.translation.xy());
+
+        build_molecule_event.send(BuildMolecule::Add {
+            target: event.b_id,
+            element,
+            near,
+        });
+
+        growth.remaining -= 1;
+        absorbed.push(event.a_id);
         cmds.entity(event.a_id).despawn();
     }
 }
 
 pub fn plugin(app: &mut App) {
     app.add_event::<CollisionEvent<Powerup, Player>>()
-        .add_systems(Startup, setup_powerup_arrow)
-        .add_systems(Update, spawn_powerup_system)
-        .add_systems(Update, powerup_arrow_system)
+        .add_event::<CollisionEvent<Powerup, Enemy>>()
+        .init_resource::<EnemyGrowth>()
         .add_systems(
-            Update,
+            FixedUpdate,
+            (
+                spawn_powerup_system,
+                powerup_refund_system.before(time_to_live),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
             (
                 collision_system::<Powerup, Player>,
                 powerup_player_collision_system,
+                collision_system::<Powerup, Enemy>,
+                powerup_enemy_collision_system,
             )
-                .chain(),
+                .chain()
+                .in_set(BuildMoleculeSet::Powerups),
         );
 }
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(Update, powerup_blink_system);
+}
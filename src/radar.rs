@@ -0,0 +1,205 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    boss::Boss,
+    enemy::Enemy,
+    molecule::Molecule,
+    powerup::Powerup,
+    state::{GameState, PlayingCleanup},
+    Player,
+};
+
+const RADAR_SIZE: f32 = 240.;
+
+const BLIP_SIZE: f32 = 4.;
+
+const MAX_BLIP_SIZE: f32 = 24.;
+
+#[derive(Resource)]
+pub struct RadarSettings {
+    /// How far from the player the edge of the radar reaches, in world units.
+    pub range: f32,
+}
+
+impl Default for RadarSettings {
+    fn default() -> Self {
+        // Enemies spawn up to 6000 away, so they show up as soon as they arrive
+        Self { range: 7000. }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlipKind {
+    Player,
+    Enemy,
+    Boss,
+    Powerup,
+}
+
+impl BlipKind {
+    fn color(&self) -> Color {
+        match self {
+            BlipKind::Player => Color::srgb(0.4, 0.8, 1.),
+            BlipKind::Enemy => Color::srgb(1., 0.7, 0.3),
+            BlipKind::Boss => Color::srgb(1., 0.3, 0.3),
+            BlipKind::Powerup => Color::srgb(0.6, 1., 0.6),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Radar;
+
+#[derive(Component)]
+pub struct RadarBlip {
+    pub target: Entity,
+}
+
+/// Where something `offset` from the player shows up on a radar of this `radius`,
+/// relative to its middle in UI coordinates, or `None` if it's out of `range`.
+pub fn radar_position(offset: Vec2, range: f32, radius: f32) -> Option<Vec2> {
+    if offset.length() > range {
+        return None;
+    }
+
+    let point = offset / range * radius;
+    Some(Vec2::new(point.x, -point.y))
+}
+
+pub fn blip_size(atoms: usize) -> f32 {
+    (BLIP_SIZE * (atoms as f32).sqrt()).clamp(BLIP_SIZE, MAX_BLIP_SIZE)
+}
+
+pub fn setup_radar(mut cmds: Commands) {
+    cmds.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(5.),
+                left: Val::Percent(5.),
+                width: Val::Px(RADAR_SIZE),
+                height: Val::Px(RADAR_SIZE),
+                overflow: Overflow::clip(),
+                ..Default::default()
+            },
+            background_color: Color::srgba(0.1, 0.2, 0.1, 0.5).into(),
+            border_radius: BorderRadius::MAX,
+            ..Default::default()
+        },
+        Radar,
+        PlayingCleanup,
+    ));
+}
+
+pub fn radar_system(
+    mut cmds: Commands,
+    settings: Res<RadarSettings>,
+    radars: Query<Entity, With<Radar>>,
+    mut blips: Query<(Entity, &RadarBlip, &mut Style, &mut Visibility)>,
+    players: Query<(Entity, &Transform, &Molecule), With<Player>>,
+    enemies: Query<(Entity, &Transform, &Molecule, Has<Boss>), With<Enemy>>,
+    powerups: Query<(Entity, &Transform), With<Powerup>>,
+) {
+    let Ok(radar) = radars.get_single() else {
+        return;
+    };
+
+    let Ok((player, player_transform, player_molecule)) = players.get_single() else {
+        for (_, _, _, mut visibility) in &mut blips {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    let center = player_transform.translation.xy();
+
+    let targets = std::iter::once((
+        player,
+        center,
+        BlipKind::Player,
+        player_molecule.elements.len(),
+    ))
+    .chain(enemies.iter().map(|(entity, transform, molecule, boss)| {
+        let kind = if boss {
+            BlipKind::Boss
+        } else {
+            BlipKind::Enemy
+        };
+        (
+            entity,
+            transform.translation.xy(),
+            kind,
+            molecule.elements.len(),
+        )
+    }))
+    .chain(
+        powerups
+            .iter()
+            .map(|(entity, transform)| (entity, transform.translation.xy(), BlipKind::Powerup, 1)),
+    );
+
+    let mut existing: HashMap<Entity, Entity> = blips
+        .iter()
+        .map(|(blip, RadarBlip { target }, _, _)| (*target, blip))
+        .collect();
+
+    for (target, pos, kind, atoms) in targets {
+        let blip = existing.remove(&target);
+
+        let Some(point) = radar_position(pos - center, settings.range, RADAR_SIZE / 2.) else {
+            if let Some(blip) = blip {
+                cmds.entity(blip).despawn_recursive();
+            }
+            continue;
+        };
+
+        let size = blip_size(atoms);
+        let left = Val::Px(RADAR_SIZE / 2. + point.x - size / 2.);
+        let top = Val::Px(RADAR_SIZE / 2. + point.y - size / 2.);
+
+        if let Some((_, _, mut style, mut visibility)) =
+            blip.and_then(|blip| blips.get_mut(blip).ok())
+        {
+            style.left = left;
+            style.top = top;
+            style.width = Val::Px(size);
+            style.height = Val::Px(size);
+            *visibility = Visibility::Inherited;
+            continue;
+        }
+
+        let blip = cmds
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left,
+                        top,
+                        width: Val::Px(size),
+                        height: Val::Px(size),
+                        ..Default::default()
+                    },
+                    background_color: kind.color().into(),
+                    border_radius: BorderRadius::MAX,
+                    // The player is drawn last so it's on top
+                    z_index: ZIndex::Local((kind == BlipKind::Player) as i32),
+                    ..Default::default()
+                },
+                RadarBlip { target },
+            ))
+            .id();
+
+        cmds.entity(radar).add_child(blip);
+    }
+
+    // Whatever's left is tracking something that's gone
+    for blip in existing.into_values() {
+        cmds.entity(blip).despawn_recursive();
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<RadarSettings>()
+        .add_systems(OnEnter(GameState::Playing), setup_radar)
+        .add_systems(Update, radar_system.run_if(in_state(GameState::Playing)));
+}
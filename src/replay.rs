@@ -0,0 +1,275 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    energy::Purchase,
+    input::{InputSet, PlayerInput},
+    rng::{reset_rng, FixedSeed, GameRng},
+    state::GameState,
+};
+
+const MAGIC: &[u8; 4] = b"AWRP";
+const VERSION: u8 = 3;
+
+/// How often a run in progress is saved, in fixed ticks,
+/// so a crash only loses the last few seconds.
+const AUTOSAVE_TICKS: usize = 64 * 10;
+
+const UP: u8 = 1 << 0;
+const DOWN: u8 = 1 << 1;
+const LEFT: u8 = 1 << 2;
+const RIGHT: u8 = 1 << 3;
+const HAS_TARGET: u8 = 1 << 4;
+const BUILD_MODE: u8 = 1 << 5;
+const PLACE: u8 = 1 << 6;
+const HAS_PURCHASE: u8 = 1 << 7;
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub inputs: Vec<PlayerInput>,
+}
+
+impl Replay {
+    /// Runs of identical inputs are stored once with a repeat count,
+    /// as the input usually doesn't change every tick.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+
+        let mut inputs = self.inputs.iter().peekable();
+
+        while let Some(input) = inputs.next() {
+            let mut repeats: u8 = 1;
+            while repeats < u8::MAX && inputs.next_if_eq(&input).is_some() {
+                repeats += 1;
+            }
+
+            let flags = [
+                (input.up, UP),
+                (input.down, DOWN),
+                (input.left, LEFT),
+                (input.right, RIGHT),
+                (input.target.is_some(), HAS_TARGET),
+                (input.build_mode, BUILD_MODE),
+                (input.place, PLACE),
+                (input.buy.is_some(), HAS_PURCHASE),
+            ]
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .fold(0, |flags, (_, flag)| flags | flag);
+
+            bytes.push(repeats);
+            bytes.push(flags);
+
+            if let Some(target) = input.target {
+                bytes.extend_from_slice(&target.x.to_le_bytes());
+                bytes.extend_from_slice(&target.y.to_le_bytes());
+            }
+
+            if let Some(purchase) = input.buy {
+                let index = Purchase::ALL.iter().position(|&p| p == purchase);
+                bytes.push(index.unwrap_or_default() as u8);
+            }
+        }
+
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut bytes = bytes.iter().copied();
+        let mut take = |count: usize| -> Option<Vec<u8>> {
+            let taken: Vec<u8> = bytes.by_ref().take(count).collect();
+            (taken.len() == count).then_some(taken)
+        };
+
+        if take(MAGIC.len())? != MAGIC || take(1)?[0] != VERSION {
+            return None;
+        }
+
+        let seed = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let mut inputs = Vec::new();
+
+        while let Some(header) = take(2) {
+            let [repeats, flags] = header[..] else {
+                return None;
+            };
+
+            let target = if flags & HAS_TARGET != 0 {
+                let mut coord = || Some(f32::from_le_bytes(take(4)?.try_into().ok()?));
+                Some(Vec2::new(coord()?, coord()?))
+            } else {
+                None
+            };
+
+            let buy = if flags & HAS_PURCHASE != 0 {
+                Some(*Purchase::ALL.get(take(1)?[0] as usize)?)
+            } else {
+                None
+            };
+
+            let input = PlayerInput {
+                up: flags & UP != 0,
+                down: flags & DOWN != 0,
+                left: flags & LEFT != 0,
+                right: flags & RIGHT != 0,
+                target,
+                build_mode: flags & BUILD_MODE != 0,
+                place: flags & PLACE != 0,
+                buy,
+            };
+
+            inputs.resize(inputs.len() + repeats as usize, input);
+        }
+
+        Some(Self { seed, inputs })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.encode())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::decode(&fs::read(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid replay file"))
+    }
+}
+
+#[derive(Resource)]
+pub struct Recorder {
+    pub path: PathBuf,
+    pub replay: Replay,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            replay: Replay::default(),
+        }
+    }
+
+    fn save(&self) -> bool {
+        let saved = self.replay.save(&self.path);
+
+        if let Err(err) = &saved {
+            error!("Couldn't save replay to {}: {err}", self.path.display());
+        }
+
+        saved.is_ok()
+    }
+}
+
+#[derive(Resource)]
+pub struct Playback {
+    pub replay: Replay,
+    pub tick: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, tick: 0 }
+    }
+}
+
+pub fn start_recording(recorder: Option<ResMut<Recorder>>, rng: Res<GameRng>) {
+    if let Some(mut recorder) = recorder {
+        recorder.replay = Replay {
+            seed: rng.seed(),
+            inputs: Vec::new(),
+        };
+    }
+}
+
+pub fn record_input(recorder: Option<ResMut<Recorder>>, input: Res<PlayerInput>) {
+    if let Some(mut recorder) = recorder {
+        recorder.replay.inputs.push(*input);
+    }
+}
+
+pub fn save_recording(recorder: Option<Res<Recorder>>) {
+    if let Some(recorder) = recorder {
+        if recorder.save() {
+            info!("Saved replay to {}", recorder.path.display());
+        }
+    }
+}
+
+pub fn autosave_recording(recorder: Option<Res<Recorder>>) {
+    if let Some(recorder) = recorder {
+        if recorder.replay.inputs.len() % AUTOSAVE_TICKS == 0 {
+            recorder.save();
+        }
+    }
+}
+
+/// Closing the window mid-run never leaves the playing state, so save then too.
+pub fn save_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    recorder: Option<Res<Recorder>>,
+    state: Res<State<GameState>>,
+) {
+    if exit.read().count() > 0 && *state == GameState::Playing {
+        save_recording(recorder);
+    }
+}
+
+pub fn setup_playback(
+    playback: Option<Res<Playback>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if playback.is_some() {
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// The seed is set on entering the run rather than at startup,
+/// as the headless app is already playing before the startup systems run.
+pub fn start_playback(playback: Option<ResMut<Playback>>, mut fixed_seed: ResMut<FixedSeed>) {
+    if let Some(mut playback) = playback {
+        playback.tick = 0;
+        fixed_seed.0 = Some(playback.replay.seed);
+    }
+}
+
+pub fn playback_input(playback: Option<ResMut<Playback>>, mut input: ResMut<PlayerInput>) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+
+    *input = playback
+        .replay
+        .inputs
+        .get(playback.tick)
+        .copied()
+        .unwrap_or_default();
+
+    playback.tick += 1;
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(Startup, setup_playback)
+        .add_systems(
+            OnEnter(GameState::Playing),
+            (
+                start_recording.after(reset_rng),
+                start_playback.before(reset_rng),
+            ),
+        )
+        // After everything else that writes the input, so that's what gets recorded
+        .add_systems(
+            FixedPreUpdate,
+            (playback_input, record_input, autosave_recording)
+                .chain()
+                .after(InputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), save_recording)
+        .add_systems(Last, save_recording_on_exit);
+}
@@ -0,0 +1,84 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+
+use crate::state::GameState;
+
+#[derive(Resource, Default)]
+pub struct FixedSeed(pub Option<u64>);
+
+/// Separate random streams, so that one system using more or fewer numbers
+/// doesn't change what every other system gets.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RngStream {
+    Waves,
+    Powerups,
+    Shooters,
+}
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    main: StdRng,
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            main: StdRng::seed_from_u64(seed),
+            streams: HashMap::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+
+        self.streams.entry(stream).or_insert_with(|| {
+            // Mix the stream in so each gets a different sequence
+            let stream = stream as u64 + 1;
+            StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        })
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.main.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.main.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.main.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.main.try_fill_bytes(dest)
+    }
+}
+
+pub fn reset_rng(mut rng: ResMut<GameRng>, fixed_seed: Res<FixedSeed>) {
+    let seed = fixed_seed.0.unwrap_or_else(|| rand::thread_rng().gen());
+
+    info!("Starting run with seed {seed}");
+    *rng = GameRng::new(seed);
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<FixedSeed>()
+        .init_resource::<GameRng>()
+        .add_systems(OnEnter(GameState::Playing), reset_rng);
+}
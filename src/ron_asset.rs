@@ -0,0 +1,76 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use serde::de::DeserializeOwned;
+
+pub struct RonAssetLoader<A> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<A>,
+}
+
+impl<A> RonAssetLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<A, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// Stats loaded from a RON asset into a resource of the same type,
+/// which is replaced whenever the asset is (re)loaded.
+pub trait RonTable: Asset + Resource + Clone {
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct TableHandle<T: Asset>(pub Handle<T>);
+
+pub fn reload_table_system<T: RonTable>(
+    mut events: EventReader<AssetEvent<T>>,
+    handle: Res<TableHandle<T>>,
+    tables: Res<Assets<T>>,
+    mut table: ResMut<T>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+            continue;
+        }
+
+        let Some(loaded) = tables.get(&handle.0) else {
+            continue;
+        };
+
+        match loaded.validate() {
+            Ok(()) => *table = loaded.clone(),
+            Err(err) => warn!("Keeping the old {}: {err}", T::short_type_path()),
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use bevy::{ecs::system::SystemId, prelude::*};
 use rand::Rng;
+use serde::Deserialize;
 use std::time::Duration;
 
 use crate::{
-    collision::{collision_system, CollisionEvent},
+    collision::{collision_system, Collider, CollisionEvent, CollisionLayers, Layer},
     elements::Atom,
     health::Health,
-    MainCamera, Player, TimeToLive, Velocity,
+    input::PlayerInput,
+    status::StatusEffects,
+    Player, TimeToLive, Velocity,
 };
 
 #[derive(Component, Clone, PartialEq, Eq)]
@@ -22,18 +25,54 @@ impl Bullet {
             Bullet::FromEnemy => "EnemyBullet.png",
         }
     }
+
+    pub fn layers(&self) -> CollisionLayers {
+        match self {
+            Bullet::FromPlayer => CollisionLayers::new(Layer::PLAYER_BULLET, Layer::ENEMY_ATOM),
+            Bullet::FromEnemy => CollisionLayers::new(Layer::ENEMY_BULLET, Layer::PLAYER_ATOM),
+        }
+    }
+}
+
+pub const BULLET_SPEED: f32 = 25.;
+
+pub const BULLET_LIFETIME: Duration = Duration::from_secs(2);
+
+pub const BULLET_RADIUS: f32 = 14.;
+
+#[derive(Component)]
+pub struct Piercing {
+    pub remaining: usize,
+    pub hit: Vec<Entity>,
+}
+
+pub struct BulletSpawn {
+    pub origin: Vec2,
+    pub dir: Vec2,
+    pub bullet: Bullet,
+    pub speed: f32,
+    pub lifetime: Duration,
+    pub pierce: usize,
+    pub collider: Collider,
 }
 
 pub fn create_bullet(
-    In((origin, dir, bullet)): In<(Vec2, Vec2, Bullet)>,
+    In(BulletSpawn {
+        origin,
+        dir,
+        bullet,
+        speed,
+        lifetime,
+        pierce,
+        collider,
+    }): In<BulletSpawn>,
     mut cmds: Commands,
     assets: ResMut<AssetServer>,
 ) {
-    let speed = 25.;
     // Just in case the caller didn't normalise it
     let dir = dir.normalize();
 
-    cmds.spawn((
+    let mut entity = cmds.spawn((
         Velocity {
             velocity: Vec3::new(dir.x, dir.y, 0.) * speed,
             drag: 0.,
@@ -41,20 +80,37 @@ pub fn create_bullet(
         },
         SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(28., 28.)),
+                custom_size: Some(match collider {
+                    Collider::Capsule {
+                        half_length,
+                        radius,
+                    } => Vec2::new(half_length + radius, radius) * 2.,
+                    _ => Vec2::splat(collider.bounding_radius() * 2.),
+                }),
                 ..Default::default()
             },
             texture: assets.load(bullet.image_path()),
-            transform: Transform::from_xyz(origin.x, origin.y, 1.),
+            // Face along the direction of travel
+            transform: Transform::from_xyz(origin.x, origin.y, 1.)
+                .with_rotation(Quat::from_rotation_z(dir.y.atan2(dir.x))),
             ..Default::default()
         },
-        TimeToLive::new(Duration::from_secs(2)),
+        TimeToLive::new(lifetime),
+        collider,
+        bullet.layers(),
         bullet,
     ));
+
+    if pierce > 0 {
+        entity.insert(Piercing {
+            remaining: pierce,
+            hit: Vec::new(),
+        });
+    }
 }
 
 #[derive(Resource)]
-pub struct CreateBullet(pub SystemId<(Vec2, Vec2, Bullet)>);
+pub struct CreateBullet(pub SystemId<BulletSpawn>);
 
 impl FromWorld for CreateBullet {
     fn from_world(world: &mut World) -> Self {
@@ -62,95 +118,213 @@ impl FromWorld for CreateBullet {
     }
 }
 
+#[derive(Clone, Copy, Deserialize)]
+pub enum Weapon {
+    /// One bullet towards the target.
+    Single,
+    /// `count` bullets fanned evenly across `angle` degrees.
+    Spread { count: usize, angle: f32 },
+    /// `count` bullets one after another, `interval` seconds apart.
+    Burst { count: usize, interval: f32 },
+    /// A fast bullet that passes through `pierce` atoms before stopping.
+    Beam { pierce: usize, speed: f32 },
+    /// `count` bullets fanned across `angle` degrees that only live for `lifetime` seconds.
+    Shotgun {
+        count: usize,
+        angle: f32,
+        lifetime: f32,
+    },
+}
+
+impl Weapon {
+    pub fn bullet_speed(&self) -> f32 {
+        match *self {
+            Weapon::Beam { speed, .. } => speed,
+            _ => BULLET_SPEED,
+        }
+    }
+
+    pub fn bullet_collider(&self) -> Collider {
+        match *self {
+            // Stretch beams out along their direction
+            Weapon::Beam { .. } => Collider::Capsule {
+                half_length: BULLET_RADIUS * 2.,
+                radius: BULLET_RADIUS * 0.75,
+            },
+            _ => Collider::Circle {
+                radius: BULLET_RADIUS,
+            },
+        }
+    }
+
+    pub fn fire(
+        &self,
+        cmds: &mut Commands,
+        create_bullet: &CreateBullet,
+        origin: Vec2,
+        dir: Vec2,
+        bullet: Bullet,
+    ) {
+        let (count, angle, lifetime, pierce) = match *self {
+            Weapon::Single | Weapon::Burst { .. } => (1, 0., BULLET_LIFETIME, 0),
+            Weapon::Spread { count, angle } => (count, angle, BULLET_LIFETIME, 0),
+            Weapon::Beam { pierce, .. } => (1, 0., BULLET_LIFETIME, pierce),
+            Weapon::Shotgun {
+                count,
+                angle,
+                lifetime,
+            } => (count, angle, Duration::from_secs_f32(lifetime), 0),
+        };
+
+        for i in 0..count {
+            // Spread the bullets evenly between -angle/2 and angle/2
+            let offset = if count > 1 {
+                angle * (i as f32 / (count - 1) as f32 - 0.5)
+            } else {
+                0.
+            };
+
+            cmds.run_system_with_input(
+                create_bullet.0,
+                BulletSpawn {
+                    origin,
+                    dir: Rot2::degrees(offset) * dir.normalize(),
+                    bullet: bullet.clone(),
+                    speed: self.bullet_speed(),
+                    lifetime,
+                    pierce,
+                    collider: self.bullet_collider(),
+                },
+            );
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Shooter {
     pub timer: Timer,
+    pub weapon: Weapon,
+    burst: Option<(usize, Timer)>,
 }
 
 impl Shooter {
-    pub fn new(duration: Duration) -> Self {
-        let mut rng = rand::thread_rng();
-
+    pub fn new(duration: Duration, weapon: Weapon, rng: &mut impl Rng) -> Self {
         let mut timer = Timer::new(duration, TimerMode::Repeating);
         timer.tick(rng.gen_range(Duration::from_secs(0)..duration));
 
-        Self { timer }
+        Self {
+            timer,
+            weapon,
+            burst: None,
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        let mut fire = false;
+
+        if let Some((remaining, timer)) = &mut self.burst {
+            if timer.tick(delta).just_finished() {
+                fire = true;
+                *remaining -= 1;
+
+                if *remaining == 0 {
+                    self.burst = None;
+                }
+            }
+        }
+
+        if self.timer.tick(delta).just_finished() {
+            fire = true;
+
+            if let Weapon::Burst { count, interval } = self.weapon {
+                self.burst = (count > 1).then(|| {
+                    (
+                        count - 1,
+                        Timer::from_seconds(interval, TimerMode::Repeating),
+                    )
+                });
+            }
+        }
+
+        fire
     }
 }
 
-fn player_shoot(
+pub fn player_shoot(
     create_bullet: Res<CreateBullet>,
-    windows: Query<&Window>,
-    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    input: Res<PlayerInput>,
     mut shooters: Query<(&GlobalTransform, &Parent, &mut Shooter)>,
-    players: Query<&Transform, With<Player>>,
+    players: Query<(&Transform, Option<&StatusEffects>), With<Player>>,
     mut cmds: Commands,
     time: Res<Time>,
 ) {
-    let (camera, camera_transform) = cameras.single();
-    let window = windows.single();
-    let Some(target) = window
-        .cursor_position()
-        .and_then(|cursor_pos| camera.viewport_to_world_2d(camera_transform, cursor_pos))
-    else {
+    let Some(target) = input.target else {
         return;
     };
 
     for (shooter_transform, parent, mut shooter) in &mut shooters {
-        shooter.timer.tick(time.delta());
-
-        let Ok(player) = players.get(parent.get()) else {
+        let Ok((player, effects)) = players.get(parent.get()) else {
             continue;
         };
 
-        if !shooter.timer.finished() {
+        let fire_rate = effects.map_or(1., StatusEffects::fire_rate);
+
+        if !shooter.tick(time.delta().mul_f32(fire_rate)) {
             continue;
         }
 
         let origin = shooter_transform.translation().xy();
         let dir = target - player.translation.xy();
 
-        cmds.run_system_with_input(create_bullet.0, (origin, dir, Bullet::FromPlayer));
+        shooter
+            .weapon
+            .fire(&mut cmds, &create_bullet, origin, dir, Bullet::FromPlayer);
     }
 }
 
 pub fn bullet_hit_system(
     mut events: EventReader<CollisionEvent<Bullet, Atom>>,
-    players: Query<Entity, With<Player>>,
-    mut healths: Query<(&mut Health, &Parent), With<Atom>>,
+    mut healths: Query<&mut Health, With<Atom>>,
+    mut piercing: Query<&mut Piercing>,
     mut cmds: Commands,
 ) {
     let damage = 100. / 8.;
 
     for CollisionEvent {
         a_id: bullet,
-        a_comp: bullet_type,
         b_id: atom,
         ..
     } in events.read()
     {
-        let Ok((mut health, parent)) = healths.get_mut(*atom) else {
-            return;
+        let Ok(mut health) = healths.get_mut(*atom) else {
+            continue;
         };
 
-        let is_player_atom = players.contains(**parent);
+        let mut piercing = piercing.get_mut(*bullet).ok();
+
+        // Don't hit the same atom twice while passing through it
+        if piercing.as_ref().is_some_and(|p| p.hit.contains(atom)) {
+            continue;
+        }
 
-        let is_hit = is_player_atom && *bullet_type == Bullet::FromEnemy
-            || !is_player_atom && *bullet_type == Bullet::FromPlayer;
+        health.damage(damage);
 
-        if is_hit {
-            health.health -= damage;
-            let _ = cmds.get_entity(*bullet).map(|mut entity| entity.despawn());
+        if let Some(piercing) = piercing.as_mut().filter(|p| p.remaining > 0) {
+            piercing.remaining -= 1;
+            piercing.hit.push(*atom);
+            continue;
         }
+
+        let _ = cmds.get_entity(*bullet).map(|mut entity| entity.despawn());
     }
 }
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<CreateBullet>()
         .add_event::<CollisionEvent<Bullet, Atom>>()
-        .add_systems(Update, player_shoot)
+        .add_systems(FixedUpdate, player_shoot)
         .add_systems(
-            Update,
+            FixedUpdate,
             (collision_system::<Bullet, Atom>, bullet_hit_system).chain(),
         );
 }
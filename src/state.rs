@@ -1,12 +1,7 @@
 use bevy::prelude::*;
 
 use crate::{
-    camera::{setup_cursor, Cursor},
-    death::death_enter_system,
-    enemy::Enemy,
-    energy::reset_energy,
-    powerup::Powerup,
-    shooting::Bullet,
+    camera::Cursor, enemy::Enemy, energy::reset_energy, powerup::Powerup, shooting::Bullet,
 };
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
@@ -34,11 +29,9 @@ pub fn cleanup_system(
     >,
     mut windows: Query<&mut Window>,
 ) {
-    let Ok(mut window) = windows.get_single_mut() else {
-        return;
-    };
-
-    window.cursor.visible = true;
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.visible = true;
+    }
 
     let mut despawn = |entity| cmds.entity(entity).despawn_recursive();
     entities.iter().for_each(&mut despawn);
@@ -46,7 +39,6 @@ pub fn cleanup_system(
 
 pub fn plugin(app: &mut App) {
     app.init_state::<GameState>()
-        .add_systems(OnEnter(GameState::Playing), (setup_cursor, reset_energy))
-        .add_systems(OnExit(GameState::Playing), cleanup_system)
-        .add_systems(OnEnter(GameState::Death), death_enter_system);
+        .add_systems(OnEnter(GameState::Playing), reset_energy)
+        .add_systems(OnExit(GameState::Playing), cleanup_system);
 }
@@ -0,0 +1,194 @@
+use std::mem::discriminant;
+
+use bevy::prelude::*;
+
+use crate::{
+    elements::Atom,
+    health::Health,
+    powerup::Powerup,
+    state::{GameState, PlayingCleanup},
+    Player,
+};
+
+pub const MAGNET_RANGE: f32 = 2500.;
+
+pub const MAGNET_SPEED: f32 = 24.;
+
+/// How much damage a shield soaks up, as a fraction of each atom's max health.
+pub const SHIELD_STRENGTH: f32 = 1.;
+
+pub const SHIELD_DURATION: f32 = 10.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatusEffect {
+    FireRate(f32),
+    Shield,
+    Magnet,
+}
+
+#[derive(Component, Default)]
+pub struct StatusEffects(pub Vec<(StatusEffect, Timer)>);
+
+impl StatusEffects {
+    /// Start an effect, or restart it if it's already going.
+    pub fn add(&mut self, effect: StatusEffect, secs: f32) {
+        self.0
+            .retain(|(other, _)| discriminant(other) != discriminant(&effect));
+        self.0
+            .push((effect, Timer::from_seconds(secs, TimerMode::Once)));
+    }
+
+    pub fn has(&self, effect: StatusEffect) -> bool {
+        self.0
+            .iter()
+            .any(|(other, _)| discriminant(other) == discriminant(&effect))
+    }
+
+    pub fn fire_rate(&self) -> f32 {
+        self.0
+            .iter()
+            .filter_map(|(effect, _)| match effect {
+                StatusEffect::FireRate(rate) => Some(rate),
+                _ => None,
+            })
+            .product()
+    }
+}
+
+pub fn repair(children: &Children, healths: &mut Query<&mut Health, With<Atom>>) {
+    let mut atoms = healths.iter_many_mut(children);
+    while let Some(mut health) = atoms.fetch_next() {
+        health.health = health.max_health;
+    }
+}
+
+pub fn shield(
+    children: &Children,
+    healths: &mut Query<&mut Health, With<Atom>>,
+    effects: &mut StatusEffects,
+) {
+    let mut atoms = healths.iter_many_mut(children);
+    while let Some(mut health) = atoms.fetch_next() {
+        health.shield = health.max_health * SHIELD_STRENGTH;
+    }
+
+    effects.add(StatusEffect::Shield, SHIELD_DURATION);
+}
+
+pub fn status_effect_system(
+    time: Res<Time>,
+    mut molecules: Query<(&mut StatusEffects, &Children)>,
+    mut healths: Query<&mut Health, With<Atom>>,
+) {
+    for (mut effects, children) in &mut molecules {
+        for (_, timer) in &mut effects.0 {
+            timer.tick(time.delta());
+        }
+
+        let shield_ended = effects
+            .0
+            .iter()
+            .any(|(effect, timer)| *effect == StatusEffect::Shield && timer.finished());
+
+        if shield_ended {
+            let mut atoms = healths.iter_many_mut(children);
+            while let Some(mut health) = atoms.fetch_next() {
+                health.shield = 0.;
+            }
+        }
+
+        effects.0.retain(|(_, timer)| !timer.finished());
+    }
+}
+
+pub fn magnet_system(
+    molecules: Query<(&Transform, &StatusEffects), Without<Powerup>>,
+    mut powerups: Query<&mut Transform, With<Powerup>>,
+) {
+    for (transform, effects) in &molecules {
+        if !effects.has(StatusEffect::Magnet) {
+            continue;
+        }
+
+        let center = transform.translation.xy();
+
+        for mut powerup in &mut powerups {
+            let offset = center - powerup.translation.xy();
+
+            if offset.length() < MAGNET_RANGE {
+                let step = offset.clamp_length_max(MAGNET_SPEED);
+                powerup.translation += step.extend(0.);
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StatusText;
+
+pub fn setup_status_text(mut cmds: Commands) {
+    cmds.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Percent(5.),
+                right: Val::Percent(5.),
+                ..Default::default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 32.,
+                    ..Default::default()
+                },
+            )
+            .with_justify(JustifyText::Right),
+            ..Default::default()
+        },
+        StatusText,
+        PlayingCleanup,
+    ));
+}
+
+pub fn update_status_text(
+    mut texts: Query<&mut Text, With<StatusText>>,
+    players: Query<&StatusEffects, With<Player>>,
+) {
+    let Ok(mut text) = texts.get_single_mut() else {
+        return;
+    };
+
+    let Ok(effects) = players.get_single() else {
+        text.sections[0].value.clear();
+        return;
+    };
+
+    text.sections[0].value = effects
+        .0
+        .iter()
+        .map(|(effect, timer)| {
+            let name = match effect {
+                StatusEffect::FireRate(rate) => format!("Fire rate x{rate}"),
+                StatusEffect::Shield => "Shield".to_string(),
+                StatusEffect::Magnet => "Magnet".to_string(),
+            };
+
+            format!("{name} {:.0}\n", timer.remaining_secs().ceil())
+        })
+        .collect();
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        (status_effect_system, magnet_system).run_if(in_state(GameState::Playing)),
+    );
+}
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_status_text)
+        .add_systems(
+            Update,
+            update_status_text.run_if(in_state(GameState::Playing)),
+        );
+}
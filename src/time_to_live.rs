@@ -8,9 +8,13 @@ impl TimeToLive {
     pub fn new(duration: Duration) -> Self {
         Self(Timer::new(duration, TimerMode::Once))
     }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.remaining()
+    }
 }
 
-fn time_to_live(
+pub fn time_to_live(
     mut commands: Commands,
     time: Res<Time>,
     mut query: Query<(Entity, &mut TimeToLive)>,
@@ -23,5 +27,5 @@ fn time_to_live(
 }
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(Update, time_to_live);
+    app.add_systems(FixedUpdate, time_to_live);
 }
@@ -33,39 +33,58 @@ pub fn game_end_system(
 #[derive(Component)]
 pub struct TimerText;
 
+/// The row at the top of the screen the timer sits in, which other HUD text can join.
+#[derive(Component)]
+pub struct TimerRow;
+
 pub fn setup_timer(mut cmds: Commands) {
-    cmds.spawn((
-        TextBundle {
-            style: Style {
-                top: Val::Percent(5.),
-                justify_self: JustifySelf::Center,
+    let row = cmds
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    top: Val::Percent(5.),
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(96.),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            text: Text {
-                sections: vec![
-                    TextSection::new(
-                        "".to_string(),
-                        TextStyle {
-                            font_size: 48.,
-                            ..Default::default()
-                        },
-                    ),
-                    TextSection::new(
-                        "".to_string(),
-                        TextStyle {
-                            font_size: 32.,
-                            ..Default::default()
-                        },
-                    ),
-                ],
-                justify: JustifyText::Center,
+            TimerRow,
+            PlayingCleanup,
+        ))
+        .id();
+
+    let timer = cmds
+        .spawn((
+            TextBundle {
+                text: Text {
+                    sections: vec![
+                        TextSection::new(
+                            "".to_string(),
+                            TextStyle {
+                                font_size: 48.,
+                                ..Default::default()
+                            },
+                        ),
+                        TextSection::new(
+                            "".to_string(),
+                            TextStyle {
+                                font_size: 32.,
+                                ..Default::default()
+                            },
+                        ),
+                    ],
+                    justify: JustifyText::Center,
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            ..Default::default()
-        },
-        TimerText,
-        PlayingCleanup,
-    ));
+            TimerText,
+        ))
+        .id();
+
+    cmds.entity(row).add_child(timer);
 }
 
 pub fn update_timer(
@@ -95,13 +114,15 @@ pub fn plugin(app: &mut App) {
     app.init_resource::<GameTimer>()
         .init_resource::<HighScore>()
         .add_systems(
-            Update,
-            (game_timer_system, update_timer, game_end_system)
+            FixedUpdate,
+            (game_timer_system, game_end_system)
                 .chain()
                 .run_if(in_state(GameState::Playing)),
         )
-        .add_systems(
-            OnEnter(GameState::Playing),
-            (reset_timer_system, setup_timer),
-        );
+        .add_systems(OnEnter(GameState::Playing), reset_timer_system);
+}
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Playing), setup_timer)
+        .add_systems(Update, update_timer.run_if(in_state(GameState::Playing)));
 }
@@ -1,10 +1,40 @@
 use bevy::prelude::*;
-use rand::Rng;
-
-pub fn random_in_donut(min_dst: f32, max_dst: f32) -> Vec2 {
-    let mut rng = rand::thread_rng();
+use rand::{seq::SliceRandom, Rng};
 
+pub fn random_in_donut(rng: &mut impl Rng, min_dst: f32, max_dst: f32) -> Vec2 {
     let dst = rng.gen_range(min_dst..max_dst);
 
-    Rot2::from_rng(&mut rng) * Vec2::new(dst, 0.)
+    Rot2::from_rng(rng) * Vec2::new(dst, 0.)
+}
+
+pub fn choose_weighted<T: Clone>(rng: &mut impl Rng, pool: &[(T, u32)]) -> Option<T> {
+    pool.choose_weighted(rng, |(_, weight)| *weight)
+        .ok()
+        .map(|(item, _)| item.clone())
+}
+
+/// Where to aim to hit something `offset` away moving at `velocity`,
+/// with a projectile moving at `speed`, if it can be hit at all.
+pub fn intercept(offset: Vec2, velocity: Vec2, speed: f32) -> Option<Vec2> {
+    // Solve |offset + velocity * t| = speed * t for the earliest t > 0
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2. * offset.dot(velocity);
+    let c = offset.length_squared();
+
+    let t = if a.abs() < f32::EPSILON {
+        -c / b
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        [(-b - root) / (2. * a), (-b + root) / (2. * a)]
+            .into_iter()
+            .filter(|t| *t > 0.)
+            .min_by(f32::total_cmp)?
+    };
+
+    (t > 0. && t.is_finite()).then(|| offset + velocity * t)
 }
@@ -1,115 +1,378 @@
+use std::f32::consts::TAU;
+
 use bevy::prelude::*;
 use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
+    boss::{Boss, BossPhase},
+    collision::{CollisionLayers, Layer},
     elements::ElementInfo,
-    enemy::Enemy,
-    molecule::{build_molecules_system, BuildMolecule, Molecule},
+    enemy::{Aim, Behaviour, Enemy},
+    molecule::{BuildMolecule, BuildMoleculeSet, Molecule},
+    powerup::EnemyGrowth,
+    rng::{GameRng, RngStream},
+    ron_asset::{reload_table_system, RonAssetLoader, RonTable, TableHandle},
     state::GameState,
-    utils::random_in_donut,
+    timer::{setup_timer, TimerRow},
+    utils::{choose_weighted, random_in_donut},
     Player, Velocity,
 };
 
+/// How many waves have started, so wave `n` is `WaveTable::wave(n - 1)`.
 #[derive(Resource, Default)]
-pub struct WaveCount(usize);
+pub struct WaveCount(pub usize);
 
-pub fn reset_wave_count(mut count: ResMut<WaveCount>) {
+pub fn reset_wave_count(mut count: ResMut<WaveCount>, mut director: ResMut<WaveDirector>) {
     count.0 = 0;
+    *director = WaveDirector::default();
+}
+
+/// Decides when the next wave starts.
+///
+/// That's when the countdown runs out, or when the enemies left are
+/// less of a threat than `WaveTable::threat_threshold` of the current wave,
+/// whichever comes first. Neither happens while a boss is alive.
+#[derive(Resource, Default)]
+pub struct WaveDirector {
+    pub countdown: Timer,
+    pub wave_threat: f32,
+}
+
+#[derive(Asset, TypePath, Resource, Clone, Deserialize)]
+pub struct WaveTable {
+    /// Seconds until the next wave, for waves without their own timer.
+    pub timer: f32,
+    /// The fraction of a wave's threat left when the next wave starts early.
+    pub threat_threshold: f32,
+    #[serde(default)]
+    pub enemy_growth: usize,
+    #[serde(default)]
+    pub aim: Option<AimRule>,
+    pub waves: Vec<Wave>,
+    pub endless: Endless,
+}
+
+impl Default for WaveTable {
+    fn default() -> Self {
+        ron::de::from_str(include_str!("../assets/waves.ron"))
+            .expect("Bundled waves.ron should be valid")
+    }
+}
+
+impl WaveTable {
+    pub fn wave(&self, index: usize) -> Wave {
+        if let Some(wave) = self.waves.get(index) {
+            return wave.clone();
+        }
+
+        let endless = &self.endless;
+        let extra = index - self.waves.len();
+
+        let mut wave = endless.wave.clone();
+        for group in &mut wave.groups {
+            group.count += extra * endless.extra_per_wave;
+        }
+
+        if (extra + 1).is_multiple_of(endless.boss_every) {
+            wave.groups.extend(endless.boss.clone());
+        }
+
+        wave
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct AimRule {
+    pub from_wave: usize,
+    pub skill_per_wave: f32,
+    pub max_skill: f32,
+}
+
+impl AimRule {
+    /// How well enemies aim on wave `count`, if they lead their shots at all.
+    pub fn skill(&self, count: usize) -> Option<f32> {
+        (count >= self.from_wave).then(|| {
+            let waves = (count - self.from_wave + 1) as f32;
+            (waves * self.skill_per_wave).min(self.max_skill)
+        })
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Wave {
+    pub groups: Vec<SpawnGroup>,
+    #[serde(default)]
+    pub timer: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Endless {
+    pub wave: Wave,
+    /// Enemies added to each group for every wave past the first endless one.
+    pub extra_per_wave: usize,
+    /// Every this many endless waves also spawn `boss`, or never if 0.
+    #[serde(default)]
+    pub boss_every: usize,
+    #[serde(default)]
+    pub boss: Option<SpawnGroup>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SpawnGroup {
+    pub count: usize,
+    pub molecule: MoleculeSpawn,
+    pub speed: (f32, f32),
+    #[serde(default)]
+    pub formation: Formation,
+    /// A weighted pool each molecule's behaviour is picked from,
+    /// or just `Seek` if empty.
+    #[serde(default)]
+    pub behaviours: Vec<(Behaviour, u32)>,
+    #[serde(default)]
+    pub boss: Option<Vec<BossPhase>>,
+}
+
+#[derive(Clone, Deserialize)]
+pub enum MoleculeSpawn {
+    Random {
+        pool: Vec<(ElementInfo, u32)>,
+        size: (usize, usize),
+    },
+    Template(Vec<ElementInfo>),
+}
+
+impl MoleculeSpawn {
+    pub fn elements(&self, rng: &mut impl Rng) -> Vec<ElementInfo> {
+        match self {
+            MoleculeSpawn::Random { pool, size } => {
+                let size = rng.gen_range(size.0..=size.1);
+
+                (0..size)
+                    .map(|_| choose_weighted(rng, pool).expect("Element pools shouldn't be empty"))
+                    .collect()
+            }
+            MoleculeSpawn::Template(elements) => elements.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Formation {
+    #[default]
+    Scattered,
+    /// Bunched up within this distance of one spot.
+    Cluster(f32),
+    /// Evenly surrounding the player at this distance.
+    Ring(f32),
+    /// Side by side, this far apart, facing the player.
+    Line(f32),
+}
+
+const SPAWN_DISTANCE: (f32, f32) = (2000., 6000.);
+
+impl Formation {
+    pub fn offsets(&self, count: usize, rng: &mut impl Rng) -> Vec<Vec2> {
+        let (min, max) = SPAWN_DISTANCE;
+
+        match *self {
+            Formation::Scattered => (0..count).map(|_| random_in_donut(rng, min, max)).collect(),
+            Formation::Cluster(spread) => {
+                let center = random_in_donut(rng, min, max);
+                (0..count)
+                    .map(|_| center + random_in_donut(rng, 0., spread.max(f32::EPSILON)))
+                    .collect()
+            }
+            Formation::Ring(radius) => {
+                let start = Rot2::from_rng(rng);
+                (0..count)
+                    .map(|i| {
+                        let angle = TAU * i as f32 / count as f32;
+                        start * Rot2::radians(angle) * Vec2::new(radius, 0.)
+                    })
+                    .collect()
+            }
+            Formation::Line(spacing) => {
+                let center = random_in_donut(rng, min, max);
+                let side = center.normalize_or_zero().perp();
+                (0..count)
+                    .map(|i| center + side * spacing * (i as f32 - (count - 1) as f32 / 2.))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl RonTable for WaveTable {}
+
+pub fn load_wave_table(mut cmds: Commands, assets: Res<AssetServer>) {
+    cmds.insert_resource(TableHandle::<WaveTable>(assets.load("waves.ron")));
 }
 
 pub fn wave_check_system(
-    enemies: Query<(), With<Enemy>>,
+    enemies: Query<&Molecule, With<Enemy>>,
+    bosses: Query<(), With<Boss>>,
     mut cmds: Commands,
     mut build_molecule_event: EventWriter<BuildMolecule>,
     players: Query<&Transform, With<Player>>,
+    table: Res<WaveTable>,
     mut count: ResMut<WaveCount>,
+    mut director: ResMut<WaveDirector>,
+    mut growth: ResMut<EnemyGrowth>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
 ) {
-    if enemies.iter().len() != 0 {
+    if !bosses.is_empty() {
         return;
     }
 
-    count.0 += 1;
-    let count = count.0;
-    let (enemy_count, types, size_range, speed_range) = match count {
-        count if count <= 3 => (count * 4, vec![ElementInfo::Hydrogen], 1..=1, 16.0..17.0),
-        count if count <= 6 => (
-            count * 3,
-            vec![ElementInfo::Hydrogen, ElementInfo::Uranium],
-            1..=2,
-            16.0..20.0,
-        ),
-        count if count <= 9 => (
-            count * 2,
-            vec![
-                ElementInfo::Iron,
-                ElementInfo::Uranium,
-                ElementInfo::Thorium,
-            ],
-            1..=3,
-            18.0..22.0,
-        ),
-        count if count <= 12 => (
-            count * 2,
-            vec![
-                ElementInfo::Iron,
-                ElementInfo::Iron,
-                ElementInfo::Uranium,
-                ElementInfo::Thorium,
-            ],
-            2..=5,
-            18.0..24.0,
-        ),
-        _ => (
-            count * 2,
-            vec![
-                ElementInfo::Iron,
-                ElementInfo::Uranium,
-                ElementInfo::Thorium,
-            ],
-            2..=6,
-            18.0..24.0,
-        ),
-    };
+    let timed_out = director.countdown.tick(time.delta()).finished();
+    let threat: f32 = enemies.iter().map(Molecule::threat).sum();
+
+    if !timed_out && threat > director.wave_threat * table.threat_threshold {
+        return;
+    }
 
-    let mut rng = rand::thread_rng();
     let Ok(player) = players.get_single() else {
         return;
     };
-    let player = player.translation;
+    let player = player.translation.xy();
+
+    let wave = table.wave(count.0);
+    count.0 += 1;
+
+    director.countdown = Timer::from_seconds(wave.timer.unwrap_or(table.timer), TimerMode::Once);
+    director.wave_threat = 0.;
+    growth.remaining = table.enemy_growth;
+
+    let rng = rng.stream(RngStream::Waves);
+
+    for group in &wave.groups {
+        for offset in group.formation.offsets(group.count, rng) {
+            let molecule = Molecule::new(group.molecule.elements(rng));
+            director.wave_threat += molecule.threat();
+            let atoms = molecule.elements.len();
 
-    for _ in 0..enemy_count {
-        let size = rng.gen_range(size_range.clone());
-        let mut elements = Vec::with_capacity(size);
-        for _ in 0..size {
-            elements.push(types[rng.gen_range(0..types.len())]);
+            let id = cmds
+                .spawn((
+                    SpatialBundle::from_transform(Transform::from_translation(
+                        (player + offset).extend(0.),
+                    )),
+                    Velocity {
+                        velocity: Vec3::ZERO,
+                        drag: rng.gen_range(0.025..0.055),
+                        max_speed: Some(rng.gen_range(group.speed.0..=group.speed.1)),
+                    },
+                    molecule,
+                    Enemy::new(rng.gen_range(0.5..1.0)),
+                    choose_weighted(rng, &group.behaviours).unwrap_or_default(),
+                    CollisionLayers::new(
+                        Layer::ENEMY,
+                        Layer::ENEMY | Layer::PLAYER | Layer::POWERUP,
+                    ),
+                ))
+                .id();
+
+            if let Some(skill) = table.aim.and_then(|aim| aim.skill(count.0)) {
+                // Some enemies are better shots than others
+                cmds.entity(id).insert(Aim {
+                    skill: skill * rng.gen_range(0.5..=1.),
+                });
+            }
+
+            if let Some(phases) = &group.boss {
+                cmds.entity(id).insert(Boss::new(phases.clone(), atoms));
+            }
+
+            build_molecule_event.send(BuildMolecule::Create { target: id });
         }
+    }
+}
 
-        let id = cmds
-            .spawn((
-                SpatialBundle::from_transform(Transform::from_translation(
-                    (player.xy() + random_in_donut(2000., 6000.)).extend(0.),
-                )),
-                Velocity {
-                    velocity: Vec3::ZERO,
-                    drag: rng.gen_range(0.025..0.055),
-                    max_speed: Some(rng.gen_range(speed_range.clone())),
+#[derive(Component)]
+pub struct WaveText;
+
+pub fn setup_wave_text(mut cmds: Commands, rows: Query<Entity, With<TimerRow>>) {
+    let Ok(row) = rows.get_single() else {
+        return;
+    };
+
+    let text = cmds
+        .spawn((
+            TextBundle {
+                text: Text {
+                    sections: vec![
+                        TextSection::new(
+                            "".to_string(),
+                            TextStyle {
+                                font_size: 48.,
+                                ..Default::default()
+                            },
+                        ),
+                        TextSection::new(
+                            "".to_string(),
+                            TextStyle {
+                                font_size: 32.,
+                                ..Default::default()
+                            },
+                        ),
+                    ],
+                    ..Default::default()
                 },
-                Molecule { elements },
-                Enemy::new(rng.gen_range(0.5..1.0)),
-            ))
-            .id();
+                ..Default::default()
+            },
+            WaveText,
+        ))
+        .id();
 
-        build_molecule_event.send(BuildMolecule::Create { target: id });
-    }
+    cmds.entity(row).add_child(text);
+}
+
+pub fn update_wave_text(
+    mut texts: Query<&mut Text, With<WaveText>>,
+    count: Res<WaveCount>,
+    director: Res<WaveDirector>,
+    bosses: Query<(), With<Boss>>,
+) {
+    let Ok(mut text) = texts.get_single_mut() else {
+        return;
+    };
+
+    text.sections[0].value = format!("Wave {}\n\n", count.0);
+    text.sections[1].value = if bosses.is_empty() {
+        format!(
+            "Next wave in {:.0} secs",
+            director.countdown.remaining_secs().ceil()
+        )
+    } else {
+        "Boss fight".to_string()
+    };
 }
 
 pub fn plugin(app: &mut App) {
-    app.init_resource::<WaveCount>()
+    app.init_asset::<WaveTable>()
+        .register_asset_loader(RonAssetLoader::<WaveTable>::new(&["waves.ron"]))
+        .init_resource::<WaveTable>()
+        .init_resource::<WaveCount>()
+        .init_resource::<WaveDirector>()
+        .add_systems(Startup, load_wave_table)
+        .add_systems(Update, reload_table_system::<WaveTable>)
         .add_systems(OnEnter(GameState::Playing), reset_wave_count)
         .add_systems(
-            Update,
+            FixedUpdate,
             wave_check_system
-                .before(build_molecules_system)
+                .in_set(BuildMoleculeSet::Waves)
                 .run_if(in_state(GameState::Playing)),
         );
 }
+
+pub fn hud_plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(GameState::Playing),
+        setup_wave_text.after(setup_timer),
+    )
+    .add_systems(
+        Update,
+        update_wave_text.run_if(in_state(GameState::Playing)),
+    );
+}
@@ -0,0 +1,942 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use gmtk_2024::{
+    boss::{Boss, BossCore, BossMovement, BossPhase},
+    build::{BuildQueue, BUILD_TIME_SCALE},
+    collision::{Collider, CollisionLayers, Layer},
+    elements::{Atom, ElementInfo, ElementTable},
+    enemy::{Behaviour, Enemy},
+    energy::{powerup_cost, Energy, Purchase},
+    headless::{bot_plugin, headless_app, run_headless},
+    health::Health,
+    indicator::screen_edge,
+    input::PlayerInput,
+    molecule::{BuildMolecule, Molecule, BOND_LENGTH},
+    player::Fragment,
+    powerup::{self, EnemyGrowth, Powerup, Refund, FIRE_RATE_BOOST, MAX_POWERUPS},
+    radar::{blip_size, radar_position},
+    replay::{Playback, Recorder, Replay},
+    shooting::{Bullet, BulletSpawn, CreateBullet, BULLET_RADIUS},
+    state::GameState,
+    status::{StatusEffect, StatusEffects},
+    utils::intercept,
+    wave::{WaveCount, WaveDirector, WaveTable},
+    Player, TimeToLive, Velocity,
+};
+
+/// A headless app that has just started playing.
+fn playing_app() -> App {
+    let mut app = headless_app(Some(0));
+    app.update();
+    tick(&mut app, 1);
+    app
+}
+
+fn tick(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+fn player(app: &mut App) -> Entity {
+    app.world_mut()
+        .query_filtered::<Entity, With<Player>>()
+        .single(app.world())
+}
+
+fn atoms(app: &mut App, molecule: Entity) -> Vec<Entity> {
+    app.world_mut()
+        .query_filtered::<(Entity, &Parent), With<Atom>>()
+        .iter(app.world())
+        .filter(|(_, parent)| parent.get() == molecule)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+fn spawn_powerup(app: &mut App, powerup: Powerup, position: Vec2) -> Entity {
+    let world = app.world_mut();
+    let assets = world.resource::<AssetServer>().clone();
+    let table = world.resource::<ElementTable>().clone();
+
+    let entity = powerup::spawn_powerup(
+        &mut world.commands(),
+        &assets,
+        &table,
+        powerup,
+        position,
+        0.,
+    );
+    world.flush();
+    entity
+}
+
+fn elements(app: &App, molecule: Entity) -> Vec<ElementInfo> {
+    app.world()
+        .get::<Molecule>(molecule)
+        .unwrap()
+        .elements
+        .clone()
+}
+
+#[test]
+fn starts_with_a_single_thorium_atom() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    assert_eq!(elements(&app, player), [ElementInfo::Thorium]);
+    assert_eq!(atoms(&mut app, player).len(), 1);
+}
+
+#[test]
+fn element_tables_must_cover_every_element() {
+    let mut table = ElementTable::default();
+    assert!(table.missing().is_empty());
+
+    table.0.remove(&ElementInfo::Radium);
+    assert_eq!(table.missing(), [ElementInfo::Radium]);
+}
+
+#[test]
+fn table_changes_keep_bond_health() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    // Thorium - Hydrogen - Carbon
+    for element in [ElementInfo::Hydrogen, ElementInfo::Carbon] {
+        app.world_mut().send_event(BuildMolecule::Add {
+            target: player,
+            element,
+            near: Some(Vec2::X * 1000.),
+        });
+        tick(&mut app, 1);
+    }
+
+    let hydrogen = app.world().get::<Children>(player).unwrap()[1];
+    let table = app.world().resource::<ElementTable>();
+    let max_health =
+        table[ElementInfo::Hydrogen].max_health + table[ElementInfo::Carbon].bond_health;
+    app.world_mut().get_mut::<Health>(hydrogen).unwrap().health = max_health / 3.;
+
+    // As if the table was hot reloaded
+    app.world_mut().resource_mut::<ElementTable>().set_changed();
+    tick(&mut app, 1);
+
+    let health = app.world().get::<Health>(hydrogen).unwrap();
+    assert_eq!(health.max_health, max_health);
+    assert_eq!(health.health, max_health / 3.);
+}
+
+#[test]
+fn atoms_are_added_and_removed() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    app.world_mut().send_event(BuildMolecule::Add {
+        target: player,
+        element: ElementInfo::Hydrogen,
+        near: None,
+    });
+    tick(&mut app, 1);
+
+    assert_eq!(
+        elements(&app, player),
+        [ElementInfo::Thorium, ElementInfo::Hydrogen]
+    );
+    assert_eq!(atoms(&mut app, player).len(), 2);
+
+    let hydrogen = atoms(&mut app, player)
+        .into_iter()
+        .find(|&atom| app.world().get::<ElementInfo>(atom) == Some(&ElementInfo::Hydrogen))
+        .unwrap();
+    app.world_mut().get_mut::<Health>(hydrogen).unwrap().health = 0.;
+    tick(&mut app, 2);
+
+    assert_eq!(elements(&app, player), [ElementInfo::Thorium]);
+    assert_eq!(atoms(&mut app, player).len(), 1);
+    assert!(app.world().get_entity(hydrogen).is_none());
+}
+
+#[test]
+fn only_decay_transmutes_atoms() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    app.world_mut().send_event(BuildMolecule::Add {
+        target: player,
+        element: ElementInfo::Uranium,
+        near: None,
+    });
+    tick(&mut app, 1);
+
+    let atom = |app: &mut App, element| {
+        atoms(app, player)
+            .into_iter()
+            .find(|&atom| app.world().get::<ElementInfo>(atom) == Some(&element))
+            .unwrap()
+    };
+
+    // Shot down, so it's gone for good
+    let uranium = atom(&mut app, ElementInfo::Uranium);
+    app.world_mut().get_mut::<Health>(uranium).unwrap().health = 0.;
+    tick(&mut app, 2);
+
+    assert_eq!(elements(&app, player), [ElementInfo::Thorium]);
+
+    // Decayed, so it becomes radium
+    let thorium = atom(&mut app, ElementInfo::Thorium);
+    let mut health = app.world_mut().get_mut::<Health>(thorium).unwrap();
+    health.health = 0.001;
+    health.shield = 20.;
+    tick(&mut app, 2);
+
+    assert_eq!(elements(&app, player), [ElementInfo::Radium]);
+    let health = app.world().get::<Health>(thorium).unwrap();
+    assert!(health.health > health.max_health - 1.);
+    assert_eq!(health.shield, 20.);
+}
+
+#[test]
+fn atoms_transmute_while_their_molecule_splits() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    // Uranium - Thorium - Hydrogen - Thorium
+    for (element, side) in [
+        (ElementInfo::Hydrogen, Vec2::X),
+        (ElementInfo::Thorium, Vec2::X),
+        (ElementInfo::Uranium, Vec2::NEG_X),
+    ] {
+        app.world_mut().send_event(BuildMolecule::Add {
+            target: player,
+            element,
+            near: Some(side * 1000.),
+        });
+        tick(&mut app, 1);
+    }
+
+    let [_, hydrogen, thorium, uranium] = app.world().get::<Children>(player).unwrap()[..] else {
+        panic!("expected four atoms");
+    };
+
+    // The hydrogen is shot down in the same tick as both ends decay,
+    // and splitting the molecule comes first
+    app.world_mut().send_event(BuildMolecule::RemoveAtom {
+        target: player,
+        atom: hydrogen,
+    });
+    for (atom, element) in [
+        (thorium, ElementInfo::Radium),
+        (uranium, ElementInfo::Thorium),
+    ] {
+        app.world_mut().send_event(BuildMolecule::Transmute {
+            target: player,
+            atom,
+            element,
+        });
+    }
+    tick(&mut app, 1);
+
+    assert_eq!(
+        elements(&app, player),
+        [ElementInfo::Thorium, ElementInfo::Thorium]
+    );
+    assert_eq!(
+        app.world().get::<ElementInfo>(uranium),
+        Some(&ElementInfo::Thorium)
+    );
+
+    let fragment = app.world().get::<Parent>(thorium).unwrap().get();
+    assert_ne!(fragment, player);
+    assert_eq!(elements(&app, fragment), [ElementInfo::Radium]);
+    assert_eq!(
+        app.world().get::<ElementInfo>(thorium),
+        Some(&ElementInfo::Radium)
+    );
+}
+
+#[test]
+fn molecules_split_and_fragments_are_recovered() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    for element in [
+        ElementInfo::Hydrogen,
+        ElementInfo::Iron,
+        ElementInfo::Carbon,
+    ] {
+        // Always on the far right, to make a chain
+        app.world_mut().send_event(BuildMolecule::Add {
+            target: player,
+            element,
+            near: Some(Vec2::X * 1000.),
+        });
+        tick(&mut app, 1);
+    }
+    assert_eq!(atoms(&mut app, player).len(), 4);
+
+    // Thorium - Hydrogen - Iron - Carbon, so losing the iron leaves the carbon on its own
+    let iron = atoms(&mut app, player)
+        .into_iter()
+        .find(|&atom| app.world().get::<ElementInfo>(atom) == Some(&ElementInfo::Iron))
+        .unwrap();
+    app.world_mut().get_mut::<Health>(iron).unwrap().health = 0.;
+    tick(&mut app, 2);
+
+    assert_eq!(
+        elements(&app, player),
+        [ElementInfo::Thorium, ElementInfo::Hydrogen]
+    );
+
+    let fragment = app
+        .world_mut()
+        .query_filtered::<Entity, With<Fragment>>()
+        .single(app.world());
+    assert_eq!(elements(&app, fragment), [ElementInfo::Carbon]);
+    assert_eq!(atoms(&mut app, fragment).len(), 1);
+    assert!(app.world().get::<Velocity>(fragment).is_some());
+
+    // Fly back into it
+    let position = app.world().get::<Transform>(player).unwrap().translation;
+    app.world_mut()
+        .get_mut::<Transform>(fragment)
+        .unwrap()
+        .translation = position;
+    tick(&mut app, 3);
+
+    assert!(app.world().get_entity(fragment).is_none());
+    assert_eq!(
+        elements(&app, player),
+        [
+            ElementInfo::Thorium,
+            ElementInfo::Hydrogen,
+            ElementInfo::Carbon
+        ]
+    );
+}
+
+#[test]
+fn molecules_stay_packed() {
+    // A full hexagon of seven, and the ring of twelve around that
+    for (atoms, rings) in [(7, 1.), (19, 2.)] {
+        let molecule = Molecule::new(vec![ElementInfo::Hydrogen; atoms]);
+
+        for i in 0..atoms {
+            assert!(molecule.position(i).length() <= rings * BOND_LENGTH + 0.01);
+        }
+    }
+}
+
+#[test]
+fn atoms_attach_where_they_touch() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let position = app
+        .world()
+        .get::<Transform>(player)
+        .unwrap()
+        .translation
+        .xy();
+
+    app.world_mut().send_event(BuildMolecule::Add {
+        target: player,
+        element: ElementInfo::Hydrogen,
+        near: Some(position + Vec2::new(0., 500.)),
+    });
+    tick(&mut app, 1);
+
+    let molecule = app.world().get::<Molecule>(player).unwrap();
+    let offset = molecule.position(1);
+    assert!(offset.y > 0. && offset.x.abs() < offset.y, "{offset}");
+    assert!((offset.length() - BOND_LENGTH).abs() < 0.01);
+
+    let hydrogen = atoms(&mut app, player)
+        .into_iter()
+        .find(|&atom| app.world().get::<ElementInfo>(atom) == Some(&ElementInfo::Hydrogen))
+        .unwrap();
+    assert_eq!(
+        app.world()
+            .get::<Transform>(hydrogen)
+            .unwrap()
+            .translation
+            .xy(),
+        offset
+    );
+
+    // Collision is per atom, not one circle around the whole molecule
+    tick(&mut app, 1);
+    let Some(Collider::Compound(circles)) = app.world().get::<Collider>(player) else {
+        panic!("the player should have a compound collider");
+    };
+    assert_eq!(circles.len(), 2);
+}
+
+#[test]
+fn enemy_bullets_damage_the_player() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let atom = atoms(&mut app, player)[0];
+    let max_health = app.world().get::<Health>(atom).unwrap().max_health;
+
+    let create_bullet = app.world().resource::<CreateBullet>().0;
+    app.world_mut()
+        .run_system_with_input(
+            create_bullet,
+            BulletSpawn {
+                origin: Vec2::ZERO,
+                dir: Vec2::X,
+                bullet: Bullet::FromEnemy,
+                speed: 0.,
+                lifetime: Duration::from_secs(2),
+                pierce: 0,
+                collider: Collider::Circle {
+                    radius: BULLET_RADIUS,
+                },
+            },
+        )
+        .unwrap();
+    tick(&mut app, 3);
+
+    let health = app.world().get::<Health>(atom).unwrap().health;
+    assert!(
+        (max_health - 12.5 - health).abs() < 1.,
+        "health is {health}"
+    );
+
+    let bullets = app.world_mut().query::<&Bullet>().iter(app.world()).count();
+    assert_eq!(bullets, 0);
+}
+
+#[test]
+fn energy_buys_repairs_and_shields() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let atom = atoms(&mut app, player)[0];
+    let max_health = app.world().get::<Health>(atom).unwrap().max_health;
+
+    let buy = |app: &mut App, purchase| {
+        app.world_mut().resource_mut::<PlayerInput>().buy = Some(purchase);
+        tick(app, 1);
+        app.world_mut().resource_mut::<PlayerInput>().buy = None;
+    };
+
+    // Can't afford it yet, and thorium slowly decays
+    app.world_mut().get_mut::<Health>(atom).unwrap().health = 10.;
+    buy(&mut app, Purchase::Repair);
+    assert!(app.world().get::<Health>(atom).unwrap().health <= 10.);
+
+    app.world_mut().resource_mut::<Energy>().0 = 100.;
+    buy(&mut app, Purchase::Repair);
+    assert!(app.world().get::<Health>(atom).unwrap().health > max_health - 1.);
+    // Some may have gone on a random powerup too
+    assert!(app.world().resource::<Energy>().0 <= 100. - 1.5 * powerup_cost(1));
+
+    buy(&mut app, Purchase::Shield);
+    assert!(app
+        .world()
+        .get::<StatusEffects>(player)
+        .unwrap()
+        .has(StatusEffect::Shield));
+
+    let before = app.world().get::<Health>(atom).unwrap().health;
+    app.world_mut()
+        .get_mut::<Health>(atom)
+        .unwrap()
+        .damage(12.5);
+    let health = app.world().get::<Health>(atom).unwrap();
+    assert_eq!(health.health, before);
+    assert_eq!(health.shield, max_health - 12.5);
+}
+
+#[test]
+fn next_wave_starts_when_enemies_are_gone() {
+    let mut app = playing_app();
+
+    let enemies = |app: &mut App| {
+        app.world_mut()
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(app.world())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(app.world().resource::<WaveCount>().0, 1);
+    assert_eq!(enemies(&mut app).len(), 4);
+
+    for enemy in enemies(&mut app) {
+        app.world_mut().entity_mut(enemy).despawn_recursive();
+    }
+    tick(&mut app, 1);
+
+    assert_eq!(app.world().resource::<WaveCount>().0, 2);
+    assert_eq!(enemies(&mut app).len(), 8);
+}
+
+#[test]
+fn next_wave_starts_when_the_countdown_runs_out() {
+    let mut app = playing_app();
+
+    let mut director = app.world_mut().resource_mut::<WaveDirector>();
+    let duration = director.countdown.duration();
+    director.countdown.set_elapsed(duration);
+    tick(&mut app, 1);
+
+    assert_eq!(app.world().resource::<WaveCount>().0, 2);
+}
+
+#[test]
+fn next_wave_starts_when_few_enemies_are_left() {
+    let mut app = playing_app();
+    let enemies = |app: &mut App| {
+        app.world_mut()
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(app.world())
+            .collect::<Vec<_>>()
+    };
+
+    // One of the four first enemies is still too much of a threat
+    for enemy in enemies(&mut app).into_iter().skip(1) {
+        app.world_mut().entity_mut(enemy).despawn_recursive();
+    }
+    tick(&mut app, 1);
+    assert_eq!(app.world().resource::<WaveCount>().0, 1);
+
+    // but one of the next eight isn't
+    for enemy in enemies(&mut app) {
+        app.world_mut().entity_mut(enemy).despawn_recursive();
+    }
+    tick(&mut app, 1);
+    for enemy in enemies(&mut app).into_iter().skip(1) {
+        app.world_mut().entity_mut(enemy).despawn_recursive();
+    }
+    tick(&mut app, 1);
+    assert_eq!(app.world().resource::<WaveCount>().0, 3);
+}
+
+#[test]
+fn endless_waves_grow_and_bring_bosses() {
+    let table = WaveTable::default();
+    let authored = table.waves.len();
+    let enemies = |index| -> usize {
+        let wave = table.wave(index);
+        wave.groups
+            .iter()
+            .filter(|group| group.boss.is_none())
+            .map(|group| group.count)
+            .sum()
+    };
+    let bosses = |index| {
+        table
+            .wave(index)
+            .groups
+            .iter()
+            .filter(|group| group.boss.is_some())
+            .count()
+    };
+
+    assert_eq!(
+        enemies(authored + 1) - enemies(authored),
+        table.endless.extra_per_wave
+    );
+    assert_eq!(bosses(authored), 0);
+    assert_eq!(bosses(authored + table.endless.boss_every - 1), 1);
+}
+
+#[test]
+fn boss_cores_go_last() {
+    let mut app = playing_app();
+
+    let phase = |atoms_left, movement| BossPhase {
+        atoms_left,
+        movement,
+        fire_rate: 1.,
+        weapon: None,
+    };
+    let boss = app
+        .world_mut()
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(5000., 0., 0.)),
+            Velocity {
+                velocity: Vec3::ZERO,
+                drag: 0.05,
+                max_speed: None,
+            },
+            Molecule::new(vec![
+                ElementInfo::Iron,
+                ElementInfo::Hydrogen,
+                ElementInfo::Hydrogen,
+            ]),
+            Enemy::new(0.),
+            Boss::new(
+                vec![
+                    phase(1., BossMovement::Seek),
+                    phase(0.5, BossMovement::Orbit(1000.)),
+                ],
+                3,
+            ),
+        ))
+        .id();
+    app.world_mut()
+        .send_event(BuildMolecule::Create { target: boss });
+    tick(&mut app, 2);
+
+    let core = app
+        .world_mut()
+        .query_filtered::<Entity, With<BossCore>>()
+        .single(app.world());
+    assert_eq!(
+        app.world().get::<ElementInfo>(core),
+        Some(&ElementInfo::Iron)
+    );
+
+    // The core can't be destroyed yet
+    app.world_mut().get_mut::<Health>(core).unwrap().health = 0.;
+    tick(&mut app, 2);
+    assert_eq!(atoms(&mut app, boss).len(), 3);
+
+    for atom in atoms(&mut app, boss) {
+        if atom != core {
+            app.world_mut().get_mut::<Health>(atom).unwrap().health = 0.;
+        }
+    }
+    tick(&mut app, 2);
+    assert_eq!(atoms(&mut app, boss), [core]);
+    assert_eq!(app.world().get::<Boss>(boss).unwrap().phase, 1);
+
+    app.world_mut().get_mut::<Health>(core).unwrap().health = 0.;
+    tick(&mut app, 2);
+    assert!(app.world().get_entity(boss).is_none());
+}
+
+#[test]
+fn kamikazes_explode_on_the_player() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let atom = atoms(&mut app, player)[0];
+    let max_health = app.world().get::<Health>(atom).unwrap().max_health;
+
+    let kamikaze = app
+        .world_mut()
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(50., 0., 0.)),
+            Velocity {
+                velocity: Vec3::ZERO,
+                drag: 0.05,
+                max_speed: None,
+            },
+            Molecule::new(vec![ElementInfo::Hydrogen]),
+            Enemy::new(1.),
+            Behaviour::Kamikaze {
+                radius: 300.,
+                damage: 40.,
+            },
+        ))
+        .id();
+    app.world_mut()
+        .send_event(BuildMolecule::Create { target: kamikaze });
+    tick(&mut app, 5);
+
+    assert!(app.world().get_entity(kamikaze).is_none());
+    assert!(app.world().get::<Health>(atom).unwrap().health <= max_health - 40.);
+}
+
+#[test]
+fn powerups_add_atoms_when_picked_up() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    let powerup = spawn_powerup(&mut app, Powerup::AddAtom(ElementInfo::Iron), Vec2::ZERO);
+    tick(&mut app, 3);
+
+    assert!(app.world().get_entity(powerup).is_none());
+    assert_eq!(
+        elements(&app, player),
+        [ElementInfo::Thorium, ElementInfo::Iron]
+    );
+    assert_eq!(atoms(&mut app, player).len(), 2);
+}
+
+#[test]
+fn powerups_expire_with_a_refund_and_are_capped() {
+    let mut app = playing_app();
+
+    let powerups = |app: &mut App| {
+        app.world_mut()
+            .query_filtered::<(), With<Powerup>>()
+            .iter(app.world())
+            .count()
+    };
+
+    let expiring = app
+        .world_mut()
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(5000., 0., 0.)),
+            Powerup::AddAtom(ElementInfo::Iron),
+            TimeToLive::new(Duration::from_millis(100)),
+            Refund(10.),
+        ))
+        .id();
+    tick(&mut app, 10);
+
+    assert!(app.world().get_entity(expiring).is_none());
+    assert_eq!(app.world().resource::<Energy>().0, 10.);
+
+    // No more random powerups while there are already plenty
+    for i in 0..MAX_POWERUPS {
+        app.world_mut().spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(5000., i as f32 * 100., 0.)),
+            Powerup::Repair,
+        ));
+    }
+    app.world_mut().resource_mut::<Energy>().0 = 1000.;
+    tick(&mut app, 2);
+
+    assert_eq!(powerups(&mut app), MAX_POWERUPS);
+    assert_eq!(app.world().resource::<Energy>().0, 1000.);
+}
+
+#[test]
+fn special_powerups_apply_effects_that_expire() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let atom = atoms(&mut app, player)[0];
+
+    for powerup in [Powerup::FireRate, Powerup::Shield, Powerup::Magnet] {
+        spawn_powerup(&mut app, powerup, Vec2::ZERO);
+    }
+    tick(&mut app, 3);
+
+    let effects = app.world().get::<StatusEffects>(player).unwrap();
+    assert_eq!(effects.fire_rate(), FIRE_RATE_BOOST);
+    assert!(effects.has(StatusEffect::Shield));
+    assert!(effects.has(StatusEffect::Magnet));
+    assert!(app.world().get::<Health>(atom).unwrap().shield > 0.);
+
+    // The magnet pulls in powerups from a distance
+    let far = spawn_powerup(&mut app, Powerup::Energy, Vec2::new(1500., 0.));
+    tick(&mut app, 5);
+    assert!(app.world().get::<Transform>(far).unwrap().translation.x < 1500.);
+
+    // Run every effect out
+    for (_, timer) in &mut app.world_mut().get_mut::<StatusEffects>(player).unwrap().0 {
+        timer.tick(Duration::from_secs(60));
+    }
+    tick(&mut app, 1);
+
+    let effects = app.world().get::<StatusEffects>(player).unwrap();
+    assert!(effects.0.is_empty());
+    assert_eq!(effects.fire_rate(), 1.);
+    assert_eq!(app.world().get::<Health>(atom).unwrap().shield, 0.);
+}
+
+#[test]
+fn build_mode_slows_time_until_the_atom_is_placed() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+
+    app.world_mut().resource_mut::<PlayerInput>().build_mode = true;
+    spawn_powerup(&mut app, Powerup::AddAtom(ElementInfo::Iron), Vec2::ZERO);
+    tick(&mut app, 3);
+
+    assert_eq!(elements(&app, player), [ElementInfo::Thorium]);
+    assert_eq!(app.world().resource::<BuildQueue>().0, [ElementInfo::Iron]);
+    assert_eq!(
+        app.world().resource::<Time<Virtual>>().relative_speed(),
+        BUILD_TIME_SCALE
+    );
+
+    // Put it underneath
+    let position = app
+        .world()
+        .get::<Transform>(player)
+        .unwrap()
+        .translation
+        .xy();
+    let mut input = app.world_mut().resource_mut::<PlayerInput>();
+    input.target = Some(position - Vec2::new(0., 500.));
+    input.place = true;
+    tick(&mut app, 15);
+
+    assert_eq!(
+        elements(&app, player),
+        [ElementInfo::Thorium, ElementInfo::Iron]
+    );
+    let offset = app.world().get::<Molecule>(player).unwrap().position(1);
+    assert!(offset.y < 0. && offset.x.abs() < -offset.y, "{offset}");
+    assert_eq!(app.world().resource::<Time<Virtual>>().relative_speed(), 1.);
+}
+
+#[test]
+fn enemies_absorb_powerups_until_the_wave_cap() {
+    let mut app = playing_app();
+
+    let enemy = app
+        .world_mut()
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(5000., 0., 0.)),
+            Velocity {
+                velocity: Vec3::ZERO,
+                drag: 0.05,
+                max_speed: Some(0.),
+            },
+            Molecule::new(vec![ElementInfo::Hydrogen]),
+            Enemy::new(0.),
+            Behaviour::Seek,
+            CollisionLayers::new(Layer::ENEMY, Layer::ENEMY | Layer::PLAYER | Layer::POWERUP),
+        ))
+        .id();
+    app.world_mut()
+        .send_event(BuildMolecule::Create { target: enemy });
+    app.world_mut().resource_mut::<EnemyGrowth>().remaining = 1;
+    tick(&mut app, 1);
+
+    let iron = Powerup::AddAtom(ElementInfo::Iron);
+
+    let first = spawn_powerup(&mut app, iron, Vec2::new(5000., 0.));
+    tick(&mut app, 3);
+
+    assert!(app.world().get_entity(first).is_none());
+    assert_eq!(
+        elements(&app, enemy),
+        [ElementInfo::Hydrogen, ElementInfo::Iron]
+    );
+    assert_eq!(app.world().resource::<EnemyGrowth>().remaining, 0);
+
+    let second = spawn_powerup(&mut app, iron, Vec2::new(5000., 0.));
+    tick(&mut app, 3);
+
+    assert!(app.world().get_entity(second).is_some());
+    assert_eq!(elements(&app, enemy).len(), 2);
+}
+
+#[test]
+fn losing_every_atom_ends_the_run() {
+    let mut app = playing_app();
+    let player = player(&mut app);
+    let atom = atoms(&mut app, player)[0];
+
+    app.world_mut().send_event(BuildMolecule::RemoveAtom {
+        target: player,
+        atom,
+    });
+    tick(&mut app, 3);
+
+    assert!(app.world().get_entity(player).is_none());
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Death
+    );
+}
+
+#[test]
+fn bot_survives_the_first_wave() {
+    let mut app = headless_app(Some(0));
+    app.add_plugins(bot_plugin);
+
+    let report = run_headless(&mut app, 64 * 60);
+
+    assert!(report.wave >= 2, "{report:?}");
+    assert!(report.survived >= Duration::from_secs(10), "{report:?}");
+}
+
+#[test]
+fn same_seed_plays_the_same_run() {
+    let run = || {
+        let mut app = headless_app(Some(7));
+        app.add_plugins(bot_plugin);
+
+        let report = run_headless(&mut app, 64 * 30);
+
+        let player = app
+            .world_mut()
+            .query_filtered::<(&Transform, &Molecule), With<Player>>()
+            .get_single(app.world())
+            .ok()
+            .map(|(transform, molecule)| (transform.translation, molecule.elements.clone()));
+
+        (report, player, app.world().resource::<Energy>().0)
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn replays_survive_encoding() {
+    let moving = PlayerInput {
+        up: true,
+        left: true,
+        target: Some(Vec2::new(-12.5, 300.)),
+        ..Default::default()
+    };
+    let building = PlayerInput {
+        build_mode: true,
+        place: true,
+        buy: Some(Purchase::Atom(ElementInfo::Carbon)),
+        ..Default::default()
+    };
+
+    let mut inputs = vec![moving; 300];
+    inputs.push(building);
+    inputs.extend([PlayerInput::default(); 3]);
+
+    let replay = Replay { seed: 42, inputs };
+
+    assert_eq!(Replay::decode(&replay.encode()), Some(replay));
+    assert_eq!(Replay::decode(b"nope"), None);
+}
+
+#[test]
+fn playback_reproduces_the_recorded_run() {
+    let ticks = 64 * 20;
+
+    let mut app = headless_app(Some(5));
+    app.add_plugins(bot_plugin).insert_resource(Recorder::new(
+        std::env::temp_dir().join("playback_reproduces_the_recorded_run.replay"),
+    ));
+    let recorded = run_headless(&mut app, ticks);
+
+    let replay = app.world().resource::<Recorder>().replay.clone();
+    let replay = Replay::decode(&replay.encode()).unwrap();
+
+    let mut app = headless_app(None);
+    app.insert_resource(Playback::new(replay));
+    let played = run_headless(&mut app, ticks);
+
+    assert_eq!(recorded, played);
+}
+
+#[test]
+fn intercepts_lead_moving_targets() {
+    let offset = Vec2::new(1000., 0.);
+    let velocity = Vec2::new(0., 10.);
+    let speed = 25.;
+
+    let lead = intercept(offset, velocity, speed).unwrap();
+    let t = lead.length() / speed;
+
+    assert!((offset + velocity * t).distance(lead) < 0.01);
+    assert!(intercept(offset, Vec2::new(30., 0.), speed).is_none());
+}
+
+#[test]
+fn indicators_sit_at_the_screen_edge() {
+    let size = Vec2::new(1000., 800.);
+
+    assert_eq!(screen_edge(Vec2::X, size, 100.), Vec2::new(900., 400.));
+    assert_eq!(screen_edge(Vec2::Y, size, 100.), Vec2::new(500., 100.));
+
+    // Corners clamp to whichever edge comes first
+    let corner = screen_edge(Vec2::new(-1., -1.), size, 100.);
+    assert!((corner - Vec2::new(200., 700.)).length() < 0.01);
+}
+
+#[test]
+fn radar_plots_things_in_range() {
+    assert_eq!(
+        radar_position(Vec2::new(500., 250.), 1000., 100.),
+        Some(Vec2::new(50., -25.))
+    );
+    assert_eq!(radar_position(Vec2::new(0., 1001.), 1000., 100.), None);
+
+    assert!(blip_size(1) < blip_size(9));
+    assert_eq!(blip_size(10_000), blip_size(1_000_000));
+}